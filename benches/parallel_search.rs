@@ -0,0 +1,92 @@
+use std::sync::Mutex;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use surreal_numbers::computer::find_best_move;
+use surreal_numbers::hackenbush::Game;
+use surreal_numbers::surreals::{SurrealContext, SurrealNumbers};
+
+// A single stalk with a small tree of alternating colors grown on top of it. It's fixed, rather than
+// random, so runs can be compared with each other.
+const POSITION: &str = "
+turn blue
+ground 0
+node 0 0 0
+node 1 0 1
+node 2 2 2
+node 3 3 3
+node 4 4 4
+node 5 5 5
+node 6 6 6
+node 7 7 7
+node 8 8 8
+node 9 9 9
+edge 0 1 blue
+edge 1 2 red
+edge 1 3 blue
+edge 2 4 red
+edge 2 5 blue
+edge 3 6 red
+edge 3 7 blue
+edge 4 8 red
+edge 4 9 blue
+";
+
+const LOOKUP_DAYS: usize = 12;
+
+fn thread_counts() -> Vec<usize> {
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut thread_counts = vec![1, max_threads];
+    thread_counts.dedup();
+    thread_counts
+}
+
+// Searches the same position on thread pools of different sizes, to check the search scales with cores.
+// On a machine with one core there's only the one-thread pool, which says nothing about scaling.
+fn bench_search(c: &mut Criterion) {
+    let context = SurrealContext::new();
+    let game = Game::from_reader(POSITION.as_bytes()).unwrap();
+
+    let mut group = c.benchmark_group("find_best_move");
+    group.sample_size(10);
+    for threads in thread_counts() {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &game, |b, game| {
+            b.iter(|| pool.install(|| find_best_move(game, &context)))
+        });
+    }
+    group.finish();
+}
+
+// Looks up every pair of neighbours on the number line in parallel, once reading the table directly and
+// once with every lookup going through one shared lock. The locked version is the current table behind a
+// mutex, not the old table itself, so it only shows what the lock costs.
+fn bench_table(c: &mut Criterion) {
+    let table = SurrealNumbers::new();
+    table.generate_until(LOOKUP_DAYS);
+    let pairs: Vec<_> = table.line_until(LOOKUP_DAYS - 1).collect::<Vec<_>>()
+        .windows(2)
+        .map(|pair| (table.value(pair[0]).clone(), table.value(pair[1]).clone()))
+        .collect();
+    let locked = Mutex::new(SurrealNumbers::new());
+    locked.lock().unwrap().generate_until(LOOKUP_DAYS);
+
+    let mut group = c.benchmark_group("table_lookups");
+    for threads in thread_counts() {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::new("lock_free", threads), &pairs, |b, pairs| {
+            b.iter(|| pool.install(|| pairs.par_iter()
+                .map(|(lower, upper)| table.simplest_between(lower, upper).unwrap())
+                .count()))
+        });
+        group.bench_with_input(BenchmarkId::new("single_lock", threads), &pairs, |b, pairs| {
+            b.iter(|| pool.install(|| pairs.par_iter()
+                .map(|(lower, upper)| locked.lock().unwrap().simplest_between(lower, upper).unwrap())
+                .count()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_search, bench_table);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use surreal_numbers::surreals::{Surreal, SurrealContext};
+
+// Construction should stay roughly flat as the table grows, since lookups binary search the number
+// line instead of scanning every number generated so far
+fn bench_lookup(c: &mut Criterion) {
+    let context = SurrealContext::new();
+    let mut group = c.benchmark_group("Surreal::new");
+    for day in [4, 8, 12, 16] {
+        // Both bounds (and the number between them) are born right around `day`, so the table holds
+        // about 2^day numbers by the time we start timing
+        let left = Surreal::from_dyadic(&context, day - 1, 0).unwrap();
+        let right = Surreal::from_dyadic(&context, 2 * day - 1, 1).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(day), &(left, right), |b, (left, right)| {
+            b.iter(|| Surreal::new(&context, black_box(&[*left]), black_box(&[*right])).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
@@ -1,16 +1,25 @@
 use crate::app::ModelMode::{Building, Playing};
 use crate::computer::find_best_move;
+use crate::games::GameValue;
 use crate::hackenbush::{Color, Game};
+use crate::surreals::SURREALS;
 use itertools::Itertools;
 use nannou::prelude::*;
 use nannou::winit::event::VirtualKeyCode;
-use petgraph::data::DataMap;
 use petgraph::graph::{EdgeIndex, NodeIndex};
-use petgraph::prelude::StableUnGraph;
 use rand::thread_rng;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
 
 const SIZE: usize = 4;
+const SAVE_FILE: &str = "position.txt";
+// The tree doubles in width every day, so past this it's just a smear
+const MAX_TREE_DAYS: usize = 10;
+const LABELLED_TREE_DAYS: usize = 4;
+
+type EdgePositions = HashMap<EdgeIndex, ((f32, f32), (f32, f32), Color)>;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum ModelMode {
@@ -24,6 +33,9 @@ pub struct Model {
     transform_data: ((f32, f32), (f32, f32)),
     mode: ModelMode,
     selected_node: Option<NodeIndex>,
+    evaluation: Option<GameValue<'static>>,
+    building_green: bool, // Whether new edges are green instead of the current player's color
+    tree_days: Option<usize>, // How many days of the surreal tree to show, if it's being shown
 }
 
 impl Model {
@@ -59,8 +71,24 @@ impl Model {
 pub fn model(app: &App) -> Model {
     let win = app.new_window().size(800, 600).view(view).build().unwrap();
 
-    let game = Game::random_triangles(SIZE, &mut thread_rng());
+    // Start from the save file if there is one, and a random position otherwise
+    let game = load_game(&save_path()).unwrap_or_else(|| Game::random_triangles(SIZE, &mut thread_rng()));
+    let transform_data = fit_transform(app, &game);
 
+    Model {
+        _window: win,
+        game,
+        transform_data,
+        mode: ModelMode::Playing,
+        selected_node: None,
+        evaluation: None,
+        building_green: false,
+        tree_days: None,
+    }
+}
+
+// Works out the scaling that puts the game in the middle of the window
+fn fit_transform(app: &App, game: &Game) -> ((f32, f32), (f32, f32)) {
     let min_x = game
         .get_graph()
         .node_weights()
@@ -80,6 +108,9 @@ pub fn model(app: &App) -> Model {
         .map(|&(_, (_, y))| y)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap();
+    // A position with nothing on it would otherwise divide by zero
+    let max_x = if max_x > min_x { max_x } else { min_x + 1.0 };
+    let max_y = if max_y > min_y { max_y } else { min_y + 1.0 };
 
     let win_max_x = app.window_rect().w() / 3.0;
     let win_max_y = app.window_rect().h() / 3.0;
@@ -91,23 +122,34 @@ pub fn model(app: &App) -> Model {
     let multiplier_y = (win_max_y - win_min_y) / (max_y - min_y);
     let offset_y = win_min_y - min_y * multiplier_y;
 
-    let transform_data = ((multiplier_x, offset_x), (multiplier_y, offset_y));
+    ((multiplier_x, offset_x), (multiplier_y, offset_y))
+}
 
-    Model {
-        _window: win,
-        game,
-        transform_data,
-        mode: ModelMode::Playing,
-        selected_node: None,
+// The file to save to and load from, which can be given as the first argument
+fn save_path() -> PathBuf {
+    std::env::args().nth(1).map_or_else(|| PathBuf::from(SAVE_FILE), PathBuf::from)
+}
+
+fn load_game(path: &Path) -> Option<Game> {
+    let file = File::open(path).ok()?;
+    match Game::from_reader(BufReader::new(file)) {
+        Ok(game) => Some(game),
+        Err(error) => {
+            println!("Couldn't load {}: {}", path.display(), error);
+            None
+        }
     }
 }
 
 pub fn event(app: &App, model: &mut Model, event: Event) {
-    match event {
-        Event::WindowEvent {
-            id: _,
-            simple: Some(event),
-        } => match event {
+    if let Event::WindowEvent {
+        id: _,
+        simple: Some(event),
+    } = event
+    {
+        match event {
+            // Nothing to click on in the tree
+            MousePressed(MouseButton::Left) if model.tree_days.is_some() => {}
             MousePressed(MouseButton::Left) => {
                 if model.mode == ModelMode::Playing {
                     let (x, y) = app.mouse.position().into();
@@ -116,7 +158,7 @@ pub fn event(app: &App, model: &mut Model, event: Event) {
                     {
                         model.game.switch_turn();
                     }
-                    let edges = get_edge_positions(model.game.get_graph(), &model.trans_func());
+                    let edges = get_edge_positions(&model.game, &model.trans_func());
                     let closest_edge = get_selected_edge(
                         app.mouse.position().into(),
                         model.game.get_turn(),
@@ -133,34 +175,33 @@ pub fn event(app: &App, model: &mut Model, event: Event) {
                     {
                         model.game.switch_turn();
                     } else if model.selected_node.is_some() {
-                        let nodes = get_node_positions(model.game.get_graph(), &model.trans_func());
+                        let nodes = get_node_positions(&model.game, &model.trans_func());
 
                         let new_node;
                         if let Some(node) = get_selected_node(app.mouse.position().into(), &nodes) {
                             new_node = node;
                         } else {
                             let new_node_pos = model.inverse_transform((x, y));
-                            let node_weight = (
-                                model
-                                    .game
-                                    .get_graph()
-                                    .node_weight(NodeIndex::new(0))
-                                    .unwrap()
-                                    .0,
-                                new_node_pos,
-                            );
-                            new_node = model.game.get_graph_mut().add_node(node_weight);
+                            new_node = model.game.add_node(new_node_pos);
+                            // Clicking on or under the ground line puts the new node on the ground
+                            if new_node_pos.1 <= 0.0 {
+                                model.game.add_ground(new_node);
+                            }
                         }
-                        let turn = model.game.get_turn();
+                        let color = if model.building_green {
+                            Color::Green
+                        } else {
+                            model.game.get_turn()
+                        };
                         model.game.get_graph_mut().add_edge(
                             model.selected_node.unwrap(),
                             new_node,
-                            turn,
+                            color,
                         );
                         model.selected_node = None;
                     } else {
                         // Get node they wanted to click on
-                        let nodes = get_node_positions(model.game.get_graph(), &model.trans_func());
+                        let nodes = get_node_positions(&model.game, &model.trans_func());
                         let closest_node = get_selected_node(app.mouse.position().into(), &nodes);
                         if let Some(node) = closest_node {
                             model.selected_node = Some(node);
@@ -169,9 +210,53 @@ pub fn event(app: &App, model: &mut Model, event: Event) {
                 }
             }
             KeyPressed(VirtualKeyCode::Return) => {
-                let value = find_best_move(&model.game).score;
-                println!("Got surreal value");
-                println!("Model evaluation is: {}", value.to_real())
+                println!("Finding best move!");
+                let value = match find_best_move(&model.game, &SURREALS) {
+                    Ok(position) => position.score,
+                    Err(error) => {
+                        println!("Couldn't evaluate the position: {}", error);
+                        return;
+                    }
+                };
+                println!("Got game value");
+                println!("Model evaluation is: {}", value);
+                // Zoom the tree out far enough to see where the evaluation sits
+                if let (Some(days), Some(number)) = (model.tree_days, value.as_number()) {
+                    model.tree_days = Some(days.max(number.birthday()).min(MAX_TREE_DAYS));
+                }
+                model.evaluation = Some(value);
+            }
+            KeyPressed(VirtualKeyCode::G) if model.mode == Building => {
+                model.building_green = !model.building_green;
+            }
+            KeyPressed(VirtualKeyCode::S) => {
+                let path = save_path();
+                match File::create(&path).and_then(|file| model.game.to_writer(BufWriter::new(file))) {
+                    Ok(()) => println!("Saved to {}", path.display()),
+                    Err(error) => println!("Couldn't save to {}: {}", path.display(), error),
+                }
+            }
+            KeyPressed(VirtualKeyCode::L) => {
+                if let Some(game) = load_game(&save_path()) {
+                    model.transform_data = fit_transform(app, &game);
+                    model.game = game;
+                    model.selected_node = None;
+                    model.evaluation = None;
+                }
+            }
+            KeyPressed(VirtualKeyCode::T) => {
+                model.tree_days = match model.tree_days {
+                    Some(_) => None,
+                    None => Some(model.evaluation.as_ref()
+                        .and_then(|value| value.as_number())
+                        .map_or(LABELLED_TREE_DAYS, |number| number.birthday().clamp(LABELLED_TREE_DAYS, MAX_TREE_DAYS))),
+                };
+            }
+            KeyPressed(VirtualKeyCode::Up) => {
+                model.tree_days = model.tree_days.map(|days| days.saturating_sub(1).max(1));
+            }
+            KeyPressed(VirtualKeyCode::Down) => {
+                model.tree_days = model.tree_days.map(|days| (days + 1).min(MAX_TREE_DAYS));
             }
             KeyPressed(VirtualKeyCode::M) => {
                 model.mode = match model.mode {
@@ -180,12 +265,16 @@ pub fn event(app: &App, model: &mut Model, event: Event) {
                 };
             }
             _ => {}
-        },
-        _ => {}
-    };
+        }
+    }
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
+    if let Some(days) = model.tree_days {
+        view_tree(app, model, days, frame);
+        return;
+    }
+
     let win = app.window_rect();
 
     let draw = app.draw();
@@ -195,7 +284,8 @@ fn view(app: &App, model: &Model, frame: Frame) {
 
     if model.mode == Building {
         let backdrop = win.pad(10.0);
-        draw.rect().xy(backdrop.xy()).wh(backdrop.wh()).color(BLACK);
+        let color = if model.building_green { DARKGREEN } else { BLACK };
+        draw.rect().xy(backdrop.xy()).wh(backdrop.wh()).color(color);
     }
 
     let backdrop = win.pad(20.0);
@@ -204,14 +294,14 @@ fn view(app: &App, model: &Model, frame: Frame) {
         .wh(backdrop.wh())
         .color(LIGHTGREY);
 
-    let edges = get_edge_positions(model.game.get_graph(), &model.trans_func());
+    let edges = get_edge_positions(&model.game, &model.trans_func());
 
     if model.mode == Playing {
         if let Some(edge) =
             get_selected_edge(app.mouse.position().into(), model.game.get_turn(), &edges)
         {
             let (start, end, color) = edges[&edge];
-            if color == model.game.get_turn() {
+            if color.playable_by(model.game.get_turn()) {
                 draw.line()
                     .start(pt2(start.0, start.1))
                     .end(pt2(end.0, end.1))
@@ -231,19 +321,19 @@ fn view(app: &App, model: &Model, frame: Frame) {
 
     if model.mode == Building {
         if let Some(node) = model.selected_node {
-            let (x, y) = model.transform(model.game.get_graph().node_weight(node).unwrap().1);
+            let (x, y) = model.transform(node_position(&model.game, node));
             draw.ellipse().x_y(x, y).radius(10.0).color(GRAY);
         }
 
-        let nodes = get_node_positions(model.game.get_graph(), &model.trans_func());
+        let nodes = get_node_positions(&model.game, &model.trans_func());
         if let Some(node) = get_selected_node(app.mouse.position().into(), &nodes) {
-            let (x, y) = model.transform(model.game.get_graph().node_weight(node).unwrap().1);
+            let (x, y) = model.transform(node_position(&model.game, node));
             draw.ellipse().x_y(x, y).radius(10.0).color(GRAY);
         }
     }
 
-    for node in model.game.get_graph().node_indices().skip(1) {
-        let (x, y) = model.transform(model.game.get_graph().node_weight(node).unwrap().1);
+    for node in model.game.get_graph().node_indices().filter(|&node| !model.game.is_ground(node)) {
+        let (x, y) = model.transform(node_position(&model.game, node));
         draw.ellipse().x_y(x, y).radius(5.0).color(BLACK);
     }
 
@@ -256,49 +346,111 @@ fn view(app: &App, model: &Model, frame: Frame) {
     draw.to_frame(app, &frame).unwrap();
 }
 
+// Draws every number born by `days`, one row per day. Reading the tree left to right gives the numbers in
+// order, so each number's column is just its place on the number line.
+fn view_tree(app: &App, model: &Model, days: usize, frame: Frame) {
+    let win = app.window_rect();
+    let draw = app.draw();
+
+    draw.background().color(LIGHTGREY);
+    let area = win.pad(20.0);
+
+    let table = SURREALS.table();
+    table.generate_until(days);
+    let count = (1 << (days + 1)) - 1;
+    let columns: HashMap<usize, usize> = table.line_until(days)
+        .enumerate()
+        .map(|(column, index)| (index, column))
+        .collect();
+    let position = |index: usize| {
+        let x = area.left() + (columns[&index] as f32 + 0.5) * area.w() / count as f32;
+        let y = area.top() - (table.birthday(index) as f32 + 0.5) * area.h() / (days + 1) as f32;
+        pt2(x, y)
+    };
+
+    for (index, _) in table.numbers().enumerate().take(count) {
+        if let Some((parent, _)) = table.parent(index) {
+            draw.line()
+                .start(position(parent))
+                .end(position(index))
+                .color(GRAY)
+                .stroke_weight(1.0);
+        }
+    }
+
+    // Show the evaluation along with its left and right options, which are the numbers it sits between
+    let highlighted = model.evaluation.as_ref()
+        .and_then(|value| value.as_number())
+        .filter(|number| number.birthday() <= days);
+    if let Some(number) = highlighted {
+        let (left, right) = number.options();
+        let options = left.iter().map(|l| (l, Color::Blue)).chain(right.iter().map(|r| (r, Color::Red)));
+        for (option, color) in options {
+            draw.line()
+                .start(position(option.canonical_id()))
+                .end(position(number.canonical_id()))
+                .color(color.get_color())
+                .stroke_weight(3.0);
+            draw.ellipse().xy(position(option.canonical_id())).radius(7.0).color(color.get_color());
+        }
+        draw.ellipse().xy(position(number.canonical_id())).radius(9.0).color(YELLOW);
+    }
+
+    for (index, _) in table.numbers().enumerate().take(count) {
+        let point = position(index);
+        draw.ellipse().xy(point).radius(3.0).color(BLACK);
+        if days <= LABELLED_TREE_DAYS {
+            draw.text(&table.value(index).to_string())
+                .xy(point - vec2(0.0, 14.0))
+                .color(BLACK)
+                .font_size(12);
+        }
+    }
+
+    draw.to_frame(app, &frame).unwrap();
+}
+
+// Ground nodes sit on the ground line at their own x, wherever they were placed
+fn node_position(game: &Game, node: NodeIndex) -> (f32, f32) {
+    let (x, y) = game.get_graph().node_weight(node).unwrap().1;
+    if game.is_ground(node) {
+        (x, 0.0)
+    } else {
+        (x, y)
+    }
+}
+
 fn get_edge_positions(
-    graph: &StableUnGraph<(bool, (f32, f32)), Color>,
+    game: &Game,
     transform: &impl Fn((f32, f32)) -> (f32, f32),
-) -> HashMap<EdgeIndex, ((f32, f32), (f32, f32), Color)> {
+) -> EdgePositions {
+    let graph = game.get_graph();
     graph
         .edge_indices()
         .map(|edge| {
             let (a, b) = graph.edge_endpoints(edge).unwrap();
-            let (first, second) = if a.index() == 0 {
-                let (x2, y2) = transform(graph.node_weight(b).unwrap().1);
-                ((x2, transform((0.0, 0.0)).1), (x2, y2))
-            } else if b.index() == 0 {
-                let (x1, y1) = transform(graph.node_weight(a).unwrap().1);
-                ((x1, transform((0.0, 0.0)).1), (x1, y1))
-            } else {
-                let (x1, y1) = transform(graph.node_weight(a).unwrap().1);
-                let (x2, y2) = transform(graph.node_weight(b).unwrap().1);
-                ((x1, y1), (x2, y2))
-            };
+            let first = transform(node_position(game, a));
+            let second = transform(node_position(game, b));
             (edge, (first, second, *graph.edge_weight(edge).unwrap()))
         })
         .collect()
 }
 
 fn get_node_positions(
-    graph: &StableUnGraph<(bool, (f32, f32)), Color>,
+    game: &Game,
     transform: &impl Fn((f32, f32)) -> (f32, f32),
 ) -> HashMap<NodeIndex, (f32, f32)> {
-    graph
+    game.get_graph()
         .node_indices()
-        .map(|node| (node, transform(graph.node_weight(node).unwrap().1)))
+        .map(|node| (node, transform(node_position(game, node))))
         .collect()
 }
 
-fn get_selected_edge(
-    point: (f32, f32),
-    color: Color,
-    edges: &HashMap<EdgeIndex, ((f32, f32), (f32, f32), Color)>,
-) -> Option<EdgeIndex> {
+fn get_selected_edge(point: (f32, f32), color: Color, edges: &EdgePositions) -> Option<EdgeIndex> {
     let distances: Vec<_> = edges
         .iter()
         .filter_map(|(&i, &(start, end, c))| {
-            if c != color {
+            if !c.playable_by(color) {
                 return None;
             }
             let length = f32::hypot(start.0 - end.0, start.1 - end.1);
@@ -1,63 +1,189 @@
 use petgraph::graph::EdgeIndex;
 use crate::hackenbush::{Color, Game};
 use petgraph::visit::{EdgeRef, IntoEdgeReferences};
-use crate::surreals::{Surreal, SurrealNumbers, SURREALS};
+use crate::games::GameValue;
+use crate::nimbers::{sprague_grundy, Nimber};
+use crate::surreals::{SurrealContext, SurrealError};
+use itertools::Itertools;
+use rayon::iter::Either;
 use rayon::prelude::*;
 
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Ord, Eq)]
-pub struct Position {
-    pub score: Surreal,
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position<'ctx> {
+    pub score: GameValue<'ctx>,
     pub best_move: Option<usize>,
 }
 
-pub fn find_best_move(game: &Game) -> Position {
-    // We can optimize this later
-    println!("Finding best move!");
-    find_best_move_subgraph(game, game.get_turn())
-}
-
-fn find_best_move_subgraph(game: &Game, player: Color) -> Position {
-    let graph = game.get_graph();
-    //println!("Graph has {} edges", graph.edge_count());
-
+/// Fails if some value in the search is a number born too late to look up
+pub fn find_best_move<'ctx>(game: &Game, context: &'ctx SurrealContext) -> Result<Position<'ctx>, SurrealError> {
+    let components = game.components();
+    let component_moves: Vec<_> = components.par_iter()
+        .map(|component| evaluate_moves(component, context))
+        .collect::<Result<_, _>>()?;
+    let values: Vec<_> = component_moves.iter()
+        .map(|moves| value_from_moves(moves, context))
+        .collect();
 
+    // A move only changes its own component, so the rest of the sum stays the same
     let mut blue_values = Vec::new();
     let mut red_values = Vec::new();
+    for (i, moves) in component_moves.into_iter().enumerate() {
+        let rest = sum(values.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, value)| value.clone()), context)?;
+        for (value, index, edge) in moves {
+            let value = rest.clone().checked_add(value)?;
+            if edge.playable_by(Color::Blue) {
+                blue_values.push((value.clone(), index));
+            }
+            if edge.playable_by(Color::Red) {
+                red_values.push((value, index));
+            }
+        }
+    }
 
-    let positions: Vec<(Position, EdgeIndex, &Color)> =
-        graph.edge_references()
-        .into_iter()
-        .map(|edge_ref| (edge_ref.weight(), edge_ref.id()))
-        .collect::<Vec<_>>()
-        .into_iter()
-        .map(|(edge, index)| {
-            let new_game = game.make_move(index);
-            let game_value = //Position {score: unsafe { Surreal::new_with_number_collection(None, None, &mut *unlocked_surreals) }, best_move: None};
-                find_best_move_subgraph(&new_game, edge.invert());
-            (game_value, index, edge)
+    let best_move = match game.get_turn() {
+        Color::Blue => best_option(&blue_values, |a, b| a > b),
+        Color::Red => best_option(&red_values, |a, b| a < b),
+        Color::Green => unreachable!("It's never green's turn"),
+    };
+
+    Ok(Position {score: sum(values, context)?, best_move: best_move.map(|m| m.index())})
+}
+
+/// The value of a position. Pieces that only meet at the ground are evaluated on their own and added up,
+/// since searching them together would try every interleaving of their moves. All-green pieces skip the
+/// search entirely and go straight to their nimbers. Fails if some value on the way is a number born too
+/// late to look up, like the sum of a few dozen blue edges.
+pub fn evaluate<'ctx>(game: &Game, context: &'ctx SurrealContext) -> Result<GameValue<'ctx>, SurrealError> {
+    let parts: Vec<_> = game.components()
+        .par_iter()
+        .map(|component| match sprague_grundy(component) {
+            Some(nimber) => Ok(Either::Left(nimber)),
+            None => Ok(Either::Right(value_from_moves(&evaluate_moves(component, context)?, context))),
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<_, SurrealError>>()?;
+    let (nimbers, values): (Vec<Nimber>, Vec<_>) = parts.into_iter().partition_map(|part| part);
+    // Nimbers add with xor, so the green pieces are combined on their own and only added to the rest once
+    let nimber = nimbers.into_iter().fold(Nimber::zero(), |total, nimber| total + nimber);
+    sum(values, context)?.checked_add(nimber.to_game(context))
+}
+
+// The value after each possible move
+fn evaluate_moves<'ctx>(game: &Game, context: &'ctx SurrealContext) -> Result<Vec<(GameValue<'ctx>, EdgeIndex, Color)>, SurrealError> {
+    game.get_graph()
+        .edge_references()
+        .map(|edge_ref| (*edge_ref.weight(), edge_ref.id()))
+        .collect::<Vec<_>>()
+        .into_par_iter() // The surreal table doesn't lock on reads, so subtrees can be searched in parallel
+        .map(|(edge, index)| Ok((evaluate(&game.make_move(index), context)?, index, edge)))
+        .collect()
+}
 
+fn value_from_moves<'ctx>(moves: &[(GameValue<'ctx>, EdgeIndex, Color)], context: &'ctx SurrealContext) -> GameValue<'ctx> {
+    let scores = |player: Color| moves.iter()
+        .filter(|(_, _, edge)| edge.playable_by(player))
+        .map(|(value, _, _)| value.clone())
+        .collect();
+    GameValue::new(context, scores(Color::Blue), scores(Color::Red))
+}
 
-    for (game_value, index, edge) in positions {
-        match edge {
-            Color::Blue => &mut blue_values,
-            Color::Red => &mut red_values,
+fn sum<'ctx>(values: impl IntoIterator<Item = GameValue<'ctx>>, context: &'ctx SurrealContext) -> Result<GameValue<'ctx>, SurrealError> {
+    values.into_iter().try_fold(GameValue::zero(context), |total, value| total.checked_add(value))
+}
+
+// Games are only partially ordered, so this finds a move that nothing else beats rather than a maximum
+fn best_option(options: &[(GameValue, EdgeIndex)], better: impl Fn(&GameValue, &GameValue) -> bool) -> Option<EdgeIndex> {
+    let mut best: Option<&(GameValue, EdgeIndex)> = None;
+    for option in options {
+        if best.is_none_or(|(value, _)| better(&option.0, value)) {
+            best = Some(option);
         }
-            .push((game_value, index))
     }
+    best.map(|(_, index)| *index)
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surreals::Surreal;
 
-    // Now calculate the simplified value of the position
-    let left_hand = blue_values.iter().max().copied();
-    let right_hand = red_values.iter().min().copied();
+    fn load(text: &str) -> Game {
+        Game::from_reader(format!("turn blue\n{}", text).as_bytes()).unwrap()
+    }
 
+    // Separate blue edges standing on one ground
+    fn blue_edges(count: usize) -> Game {
+        let mut text = "ground 0\nnode 0 0 0\n".to_string();
+        for i in 1..=count {
+            text += &format!("node {} {} 1\nedge 0 {} blue\n", i, i, i);
+        }
+        load(&text)
+    }
 
-    let surreal_value = Surreal::new( left_hand.map(|(p, _)| p.score), right_hand.map(|(p, _)| p.score));
-    let best_move = match player {
-        Color::Blue => left_hand.map(|(_, m)| m.index()),
-        Color::Red => right_hand.map(|(_, m)| m.index()),
-    };
+    #[test]
+    fn stalks_take_the_simplest_value() {
+        let context = SurrealContext::new();
+        // Blue then red is { 0 | 1 } = 1/2
+        let game = load("ground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 0 2\nedge 0 1 blue\nedge 1 2 red\n");
+        assert_eq!(evaluate(&game, &context).unwrap().as_number(), Some(Surreal::from_dyadic(&context, 1, 1).unwrap()));
+        let position = find_best_move(&game, &context).unwrap();
+        assert_eq!(position.score, evaluate(&game, &context).unwrap());
+        // Blue can only cut its own edge
+        assert_eq!(game.get_graph()[EdgeIndex::new(position.best_move.unwrap())], Color::Blue);
+    }
 
-    Position {score: surreal_value, best_move}
+    #[test]
+    fn components_add_up() {
+        let context = SurrealContext::new();
+        // A blue edge, a blue-red stalk and a red edge on its own bit of ground: 1 + 1/2 - 1
+        let game = load("ground 0 5\nnode 0 0 0\nnode 1 0 1\nnode 2 1 1\nnode 3 1 2\nnode 5 4 0\nnode 6 4 1\n\
+            edge 0 1 blue\nedge 0 2 blue\nedge 2 3 red\nedge 5 6 red\n");
+        let components = game.components();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components.iter().map(|c| c.get_graph().edge_count()).sum::<usize>(), 4);
+        for component in &components {
+            assert_eq!(component.grounds().len(), 1);
+        }
+        assert_eq!(evaluate(&game, &context).unwrap().as_number(), Some(Surreal::from_dyadic(&context, 1, 1).unwrap()));
+        assert_eq!(evaluate(&blue_edges(10), &context).unwrap().as_number(), Some(Surreal::from_dyadic(&context, 10, 0).unwrap()));
+    }
+
+    #[test]
+    fn arches_are_one_component() {
+        let context = SurrealContext::new();
+        // Either player's cut leaves the other's edge standing on its own ground, so this is { -1 | 1 } = 0
+        let game = load("ground 0 2\nnode 0 0 0\nnode 1 1 1\nnode 2 2 0\nedge 0 1 blue\nedge 1 2 red\n");
+        assert_eq!(game.components().len(), 1);
+        assert_eq!(evaluate(&game, &context).unwrap().as_number(), Some(context.zero()));
+        // An edge along the ground is a component of its own
+        let game = load("ground 0 1\nnode 0 0 0\nnode 1 1 0\nnode 2 0 1\nedge 0 1 blue\nedge 0 2 blue\n");
+        assert_eq!(game.components().len(), 2);
+        assert_eq!(evaluate(&game, &context).unwrap().as_number(), Some(Surreal::from_dyadic(&context, 2, 0).unwrap()));
+    }
+
+    #[test]
+    fn green_edges_mix_with_red_and_blue() {
+        let context = SurrealContext::new();
+        let (zero, one) = (GameValue::zero(&context), Surreal::from_dyadic(&context, 1, 0).unwrap());
+        // Blue under green is { 0, 1 | 1 } = 1*
+        let game = load("ground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 0 2\nedge 0 1 blue\nedge 1 2 green\n");
+        assert_eq!(evaluate(&game, &context).unwrap().as_number_plus_nimber(), Some((one, 1)));
+        // Green under blue is { 0, * | 0 }
+        let game = load("ground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 0 2\nedge 0 1 green\nedge 1 2 blue\n");
+        let expected = GameValue::new(&context, vec![zero.clone(), GameValue::star(&context)], vec![zero.clone()]);
+        let value = evaluate(&game, &context).unwrap();
+        assert_eq!(value, expected);
+        assert_eq!(value, GameValue::up(&context) + GameValue::star(&context));
+        // Added to a separate green edge, the stars cancel
+        let game = load("ground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 0 2\nnode 3 1 1\n\
+            edge 0 1 green\nedge 1 2 blue\nedge 0 3 green\n");
+        assert_eq!(evaluate(&game, &context).unwrap(), GameValue::up(&context));
+        assert!(evaluate(&game, &context).unwrap() > zero);
+    }
+
+    #[test]
+    fn late_sums_are_errors() {
+        let context = SurrealContext::new();
+        assert_eq!(evaluate(&blue_edges(24), &context).map(|value| value.as_number()), Err(SurrealError::BornTooLate));
+        assert_eq!(find_best_move(&blue_edges(24), &context), Err(SurrealError::BornTooLate));
+    }
 }
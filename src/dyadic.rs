@@ -0,0 +1,351 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::ops::{Add, Mul, Neg, Sub};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, ToPrimitive, Zero};
+use crate::notation::ParseError;
+use crate::surreals::SurrealError;
+
+/// An exact rational of the form numerator / 2^exponent, which is every value a surreal born on a
+/// finite day can take
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Dyadic {
+    numerator: BigInt,
+    exponent: u32, // Kept as small as possible, so the numerator is odd unless the exponent is 0
+}
+
+impl Dyadic {
+    pub fn new(numerator: impl Into<BigInt>, exponent: u32) -> Dyadic {
+        let mut numerator = numerator.into();
+        let mut exponent = exponent;
+        if numerator.is_zero() {
+            exponent = 0;
+        }
+        while exponent > 0 && (&numerator & BigInt::one()).is_zero() {
+            numerator >>= 1;
+            exponent -= 1;
+        }
+        Dyadic { numerator, exponent }
+    }
+
+    pub fn zero() -> Dyadic {
+        Dyadic::new(0, 0)
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    /// The power of two in the denominator
+    pub fn exponent(&self) -> u32 {
+        self.exponent
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.exponent == 0
+    }
+
+    /// The day this value is born on, following the simplicity rule: integers n are born on day |n|,
+    /// and a fraction with denominator 2^k is born k + 1 days after the integer below it
+    pub fn birthday(&self) -> usize {
+        let whole_part = (self.numerator.magnitude() >> self.exponent).to_usize().unwrap_or(usize::MAX);
+        if self.is_integer() {
+            whole_part
+        } else {
+            whole_part.saturating_add(self.exponent as usize + 1)
+        }
+    }
+
+    /// The simplest (earliest born) value strictly between the bounds, where `None` is unbounded.
+    /// Panics if the lower bound isn't below the upper bound, since then there's nothing between them.
+    pub fn simplest_between(lower: Option<&Dyadic>, upper: Option<&Dyadic>) -> Dyadic {
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            assert!(lower < upper, "Nothing is between {} and {}", lower, upper);
+        }
+        let zero = Dyadic::zero();
+        if lower.is_none_or(|l| *l < zero) && upper.is_none_or(|u| zero < *u) {
+            return zero;
+        }
+        if let Some(upper) = upper.filter(|u| **u <= zero) {
+            // Flip everything over to the positive side
+            let flipped_upper = lower.map(|l| -l.clone());
+            return -Dyadic::simplest_between(Some(&-upper.clone()), flipped_upper.as_ref());
+        }
+
+        // Zero is at or below the interval, so look for the smallest multiple of 1/2^k above the lower
+        // bound, using the smallest k that still fits under the upper bound
+        let lower = lower.expect("Interval above zero has a lower bound");
+        let mut exponent = 0;
+        loop {
+            let floor = if exponent >= lower.exponent {
+                &lower.numerator << (exponent - lower.exponent)
+            } else {
+                &lower.numerator >> (lower.exponent - exponent)
+            };
+            let candidate = Dyadic::new(floor + 1, exponent);
+            if upper.is_none_or(|u| candidate < *u) {
+                return candidate;
+            }
+            exponent += 1;
+        }
+    }
+
+    /// The number halfway between this and `other`
+    pub fn midpoint(&self, other: &Dyadic) -> Dyadic {
+        let sum = self + other;
+        Dyadic::new(sum.numerator, sum.exponent + 1)
+    }
+
+    /// Divides exactly, returning `None` if the quotient isn't dyadic (or `other` is zero)
+    pub fn checked_div(&self, other: &Dyadic) -> Option<Dyadic> {
+        if other.numerator.is_zero() {
+            return None;
+        }
+        // a / 2^n divided by b / 2^m is (a * 2^m) / (b * 2^n), which is only dyadic if the odd part of
+        // b divides a
+        let mut divisor = other.numerator.clone();
+        let mut shift = 0u32;
+        while (&divisor & BigInt::one()).is_zero() {
+            divisor >>= 1;
+            shift += 1;
+        }
+        if !(&self.numerator % &divisor).is_zero() {
+            return None;
+        }
+        let numerator = (&self.numerator / divisor) << other.exponent;
+        Some(Dyadic::new(numerator, self.exponent + shift))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        // Split the shift up so huge exponents underflow gradually instead of all at once
+        let mut value = self.numerator.to_f64().unwrap_or(f64::NAN);
+        let mut exponent = self.exponent;
+        while exponent > 0 {
+            let step = exponent.min(1000);
+            value /= 2f64.powi(step as i32);
+            exponent -= step;
+        }
+        value
+    }
+
+    // Scales both numerators up to a shared exponent
+    fn aligned(&self, other: &Dyadic) -> (BigInt, BigInt, u32) {
+        let exponent = self.exponent.max(other.exponent);
+        (
+            &self.numerator << (exponent - self.exponent),
+            &other.numerator << (exponent - other.exponent),
+            exponent,
+        )
+    }
+}
+
+impl From<i64> for Dyadic {
+    fn from(value: i64) -> Dyadic {
+        Dyadic::new(value, 0)
+    }
+}
+
+impl TryFrom<f64> for Dyadic {
+    type Error = SurrealError;
+
+    // Every finite float is already a dyadic rational, so this only fails for infinities and NaN
+    fn try_from(value: f64) -> Result<Dyadic, SurrealError> {
+        if !value.is_finite() {
+            return Err(SurrealError::NotDyadic);
+        }
+        let bits = value.to_bits();
+        let sign = if bits >> 63 == 0 { 1i64 } else { -1i64 };
+        let biased_exponent = ((bits >> 52) & 0x7ff) as i64;
+        let mantissa = if biased_exponent == 0 {
+            (bits & 0xfffffffffffff) << 1
+        } else {
+            (bits & 0xfffffffffffff) | 0x10000000000000
+        };
+        // value = sign * mantissa * 2^power
+        let power = biased_exponent - 1075;
+        let numerator = BigInt::from(sign) * BigInt::from(mantissa);
+        Ok(if power >= 0 {
+            Dyadic::new(numerator << power, 0)
+        } else {
+            Dyadic::new(numerator, (-power) as u32)
+        })
+    }
+}
+
+impl PartialOrd for Dyadic {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Dyadic {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b, _) = self.aligned(other);
+        a.cmp(&b)
+    }
+}
+
+impl Add for &Dyadic {
+    type Output = Dyadic;
+
+    fn add(self, other: &Dyadic) -> Dyadic {
+        let (a, b, exponent) = self.aligned(other);
+        Dyadic::new(a + b, exponent)
+    }
+}
+
+impl Add for Dyadic {
+    type Output = Dyadic;
+
+    fn add(self, other: Dyadic) -> Dyadic {
+        &self + &other
+    }
+}
+
+impl Neg for Dyadic {
+    type Output = Dyadic;
+
+    fn neg(self) -> Dyadic {
+        Dyadic { numerator: -self.numerator, exponent: self.exponent }
+    }
+}
+
+impl Sub for &Dyadic {
+    type Output = Dyadic;
+
+    fn sub(self, other: &Dyadic) -> Dyadic {
+        let (a, b, exponent) = self.aligned(other);
+        Dyadic::new(a - b, exponent)
+    }
+}
+
+impl Sub for Dyadic {
+    type Output = Dyadic;
+
+    fn sub(self, other: Dyadic) -> Dyadic {
+        &self - &other
+    }
+}
+
+impl Mul for &Dyadic {
+    type Output = Dyadic;
+
+    fn mul(self, other: &Dyadic) -> Dyadic {
+        Dyadic::new(&self.numerator * &other.numerator, self.exponent + other.exponent)
+    }
+}
+
+impl Mul for Dyadic {
+    type Output = Dyadic;
+
+    fn mul(self, other: Dyadic) -> Dyadic {
+        &self * &other
+    }
+}
+
+impl fmt::Display for Dyadic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, BigInt::one() << self.exponent)
+        }
+    }
+}
+
+impl FromStr for Dyadic {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Dyadic, ParseError> {
+        let invalid = || ParseError::InvalidNumber(text.to_string());
+        let (numerator, denominator) = match text.split_once('/') {
+            Some((numerator, denominator)) => (numerator, denominator.parse::<BigUint>().map_err(|_| invalid())?),
+            None => (text, BigUint::from(1u32)),
+        };
+        let numerator: BigInt = numerator.parse().map_err(|_| invalid())?;
+        // The denominator has to be a power of two
+        if denominator.count_ones() != 1 {
+            return Err(invalid());
+        }
+        let exponent = denominator.trailing_zeros().unwrap_or(0) as u32;
+        Ok(Dyadic::new(numerator, exponent))
+    }
+}
+
+// Saved as text like "-3/8", since the numerator can be arbitrarily large
+#[cfg(feature = "serde")]
+impl serde::Serialize for Dyadic {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Dyadic {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Dyadic, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_convert_exactly() {
+        assert_eq!(Dyadic::try_from(-0.375), Ok(Dyadic::new(-3, 3)));
+        assert_eq!(Dyadic::try_from(6.0), Ok(Dyadic::from(6)));
+        assert_eq!(Dyadic::try_from(0.0), Ok(Dyadic::zero()));
+        assert_eq!(Dyadic::try_from(-0.0), Ok(Dyadic::zero()));
+        // The smallest subnormal is 2^-1074
+        assert_eq!(Dyadic::try_from(f64::from_bits(1)), Ok(Dyadic::new(1, 1074)));
+        assert_eq!(Dyadic::try_from(-f64::from_bits(3)), Ok(Dyadic::new(-3, 1074)));
+        assert_eq!(Dyadic::new(-3, 1074).to_f64(), -f64::from_bits(3));
+        assert_eq!(Dyadic::try_from(f64::NAN), Err(SurrealError::NotDyadic));
+        assert_eq!(Dyadic::try_from(f64::NEG_INFINITY), Err(SurrealError::NotDyadic));
+    }
+
+    #[test]
+    fn division_is_exact() {
+        assert_eq!(Dyadic::new(3, 2).checked_div(&Dyadic::new(3, 1)), Some(Dyadic::new(1, 1)));
+        assert_eq!(Dyadic::from(3).checked_div(&Dyadic::new(1, 3)), Some(Dyadic::from(24)));
+        assert_eq!(Dyadic::from(-5).checked_div(&Dyadic::from(4)), Some(Dyadic::new(-5, 2)));
+        assert_eq!(Dyadic::from(1).checked_div(&Dyadic::from(3)), None);
+        assert_eq!(Dyadic::new(3, 4).checked_div(&Dyadic::from(6)), Some(Dyadic::new(1, 5)));
+        assert_eq!(Dyadic::from(1).checked_div(&Dyadic::zero()), None);
+    }
+
+    #[test]
+    fn values_print_as_fractions() {
+        assert_eq!(Dyadic::new(-3, 3).to_string(), "-3/8");
+        assert_eq!(Dyadic::new(12, 2).to_string(), "3");
+        assert_eq!(Dyadic::zero().to_string(), "0");
+        assert_eq!("-3/8".parse::<Dyadic>().unwrap(), Dyadic::new(-3, 3));
+    }
+
+    #[test]
+    fn birthdays_follow_the_number_tree() {
+        let birthdays = [((0, 0), 0), ((3, 0), 3), ((-3, 0), 3), ((1, 1), 2), ((-3, 3), 4), ((5, 2), 4), ((-7, 1), 5)];
+        for ((numerator, exponent), birthday) in birthdays {
+            assert_eq!(Dyadic::new(numerator, exponent).birthday(), birthday, "{}/2^{}", numerator, exponent);
+        }
+    }
+
+    #[test]
+    fn simplest_between_picks_the_earliest_born() {
+        let between = |lower: Option<Dyadic>, upper: Option<Dyadic>| Dyadic::simplest_between(lower.as_ref(), upper.as_ref());
+        assert_eq!(between(None, None), Dyadic::zero());
+        assert_eq!(between(Some(Dyadic::from(2)), None), Dyadic::from(3));
+        assert_eq!(between(None, Some(Dyadic::new(-1, 1))), Dyadic::from(-1));
+        assert_eq!(between(Some(Dyadic::new(1, 2)), Some(Dyadic::new(3, 2))), Dyadic::new(1, 1));
+        assert_eq!(between(Some(Dyadic::from(-2)), Some(Dyadic::new(-13, 3))), Dyadic::new(-7, 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing is between")]
+    fn simplest_between_needs_room() {
+        Dyadic::simplest_between(Some(&Dyadic::from(1)), Some(&Dyadic::from(1)));
+    }
+}
@@ -0,0 +1,504 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Neg, Sub};
+use crate::dyadic::Dyadic;
+use crate::surreals::{Surreal, SurrealContext, SurrealError};
+
+/// A game written out as its options, exactly as given. Nothing gets simplified, so the same value can
+/// have many different forms.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameForm {
+    /// Shorthand for the canonical form of a number
+    Number(Dyadic),
+    /// Shorthand for *n, which `options` expands one level at a time
+    Nimber(usize),
+    Options { left: Vec<GameForm>, right: Vec<GameForm> },
+}
+
+impl GameForm {
+    pub fn new(left: Vec<GameForm>, right: Vec<GameForm>) -> GameForm {
+        GameForm::Options { left, right }
+    }
+
+    pub fn zero() -> GameForm {
+        GameForm::new(vec![], vec![])
+    }
+
+    /// *n = { 0, *, ..., *(n - 1) | 0, *, ..., *(n - 1) }
+    pub fn nimber(n: usize) -> GameForm {
+        GameForm::Nimber(n)
+    }
+
+    /// ↑ = { 0 | * }
+    pub fn up() -> GameForm {
+        GameForm::new(vec![GameForm::zero()], vec![GameForm::nimber(1)])
+    }
+
+    /// ↓ = { * | 0 }
+    pub fn down() -> GameForm {
+        GameForm::new(vec![GameForm::nimber(1)], vec![GameForm::zero()])
+    }
+
+    /// The left and right options, expanding numbers into their canonical forms
+    pub fn options(&self) -> (Vec<GameForm>, Vec<GameForm>) {
+        match self {
+            GameForm::Options { left, right } => (left.clone(), right.clone()),
+            GameForm::Nimber(n) => ((0..*n).map(GameForm::Nimber).collect(), (0..*n).map(GameForm::Nimber).collect()),
+            GameForm::Number(value) => {
+                let (left, right) = number_options(value);
+                (
+                    left.into_iter().map(GameForm::Number).collect(),
+                    right.into_iter().map(GameForm::Number).collect(),
+                )
+            }
+        }
+    }
+
+    /// Evaluates the form as a number, failing if it (or any of its options) isn't one
+    pub fn to_surreal<'ctx>(&self, context: &'ctx SurrealContext) -> Result<Surreal<'ctx>, SurrealError> {
+        match self {
+            GameForm::Number(value) => Surreal::from_value(context, value),
+            // *n has 0 on both sides for any n > 0
+            GameForm::Nimber(0) => Ok(context.zero()),
+            GameForm::Nimber(_) => Err(SurrealError::LeftNotLessThanRight),
+            GameForm::Options { left, right } => {
+                let left = left.iter().map(|l| l.to_surreal(context)).collect::<Result<Vec<_>, _>>()?;
+                let right = right.iter().map(|r| r.to_surreal(context)).collect::<Result<Vec<_>, _>>()?;
+                Surreal::new(context, &left, &right)
+            }
+        }
+    }
+}
+
+// The canonical options of a number: n = { n - 1 | } for positive integers, and m/2^k = { m/2^k - 1/2^k |
+// m/2^k + 1/2^k } for fractions
+fn number_options(value: &Dyadic) -> (Vec<Dyadic>, Vec<Dyadic>) {
+    let zero = Dyadic::zero();
+    if *value == zero {
+        (vec![], vec![])
+    } else if value.is_integer() {
+        let one = Dyadic::from(1);
+        if *value > zero {
+            (vec![value - &one], vec![])
+        } else {
+            (vec![], vec![value + &one])
+        }
+    } else {
+        let step = Dyadic::new(1, value.exponent());
+        (vec![value - &step], vec![value + &step])
+    }
+}
+
+/// The value of a short partizan game. Anything equal to a number is kept as a `Surreal`, and everything
+/// else (stars, ups, switches and so on) is kept as its canonical form: dominated options are thrown out
+/// and reversible options are bypassed, so games with the same value always end up with the same options.
+#[derive(Clone, Debug)]
+pub enum GameValue<'ctx> {
+    Number(Surreal<'ctx>),
+    /// x + *n for some n > 0, kept as just the two parts. Its options are x, x + *, ..., x + *(n - 1) on
+    /// both sides, which `options` builds when they're asked for.
+    NumberPlusNimber { number: Surreal<'ctx>, nimber: usize },
+    Game { context: &'ctx SurrealContext, left: Vec<GameValue<'ctx>>, right: Vec<GameValue<'ctx>> },
+}
+
+impl<'ctx> GameValue<'ctx> {
+    /// Builds { left | right } in canonical form. This never fails, since any pair of option sets makes a
+    /// valid game.
+    pub fn new(context: &'ctx SurrealContext, left: Vec<GameValue<'ctx>>, right: Vec<GameValue<'ctx>>) -> GameValue<'ctx> {
+        let left = remove_dominated(left, |a, b| a <= b);
+        let right = remove_dominated(right, |a, b| b <= a);
+        if let Some(number) = simplest_number(context, &left, &right) {
+            return GameValue::Number(number);
+        }
+
+        // Bypassing doesn't change the value, so every option can be checked against the original game
+        let game = GameValue::Game { context, left, right };
+        let (mut left, mut right) = game.options();
+        loop {
+            let (new_left, bypassed_left) = bypass_reversible(&left, |l| {
+                l.options().1.into_iter().find(|lr| lr.less_or_equal(&game)).map(|lr| lr.options().0)
+            });
+            let (new_right, bypassed_right) = bypass_reversible(&right, |r| {
+                r.options().0.into_iter().find(|rl| game.less_or_equal(rl)).map(|rl| rl.options().1)
+            });
+            left = remove_dominated(new_left, |a, b| a <= b);
+            right = remove_dominated(new_right, |a, b| b <= a);
+            if !bypassed_left && !bypassed_right {
+                return match number_plus_nimber_options(&left, &right) {
+                    Some((number, nimber)) => GameValue::number_plus_nimber(number, nimber),
+                    None => GameValue::Game { context, left, right },
+                };
+            }
+        }
+    }
+
+    pub fn zero(context: &'ctx SurrealContext) -> GameValue<'ctx> {
+        GameValue::Number(context.zero())
+    }
+
+    pub fn star(context: &'ctx SurrealContext) -> GameValue<'ctx> {
+        GameValue::nimber(context, 1)
+    }
+
+    pub fn nimber(context: &'ctx SurrealContext, n: usize) -> GameValue<'ctx> {
+        GameValue::number_plus_nimber(context.zero(), n)
+    }
+
+    /// x + *n, which is just x when n is 0
+    pub fn number_plus_nimber(number: Surreal<'ctx>, nimber: usize) -> GameValue<'ctx> {
+        match nimber {
+            0 => GameValue::Number(number),
+            nimber => GameValue::NumberPlusNimber { number, nimber },
+        }
+    }
+
+    pub fn up(context: &'ctx SurrealContext) -> GameValue<'ctx> {
+        GameValue::new(context, vec![GameValue::zero(context)], vec![GameValue::star(context)])
+    }
+
+    pub fn down(context: &'ctx SurrealContext) -> GameValue<'ctx> {
+        -GameValue::up(context)
+    }
+
+    /// { left | right }, like { 1 | -1 }
+    pub fn switch(left: Surreal<'ctx>, right: Surreal<'ctx>) -> GameValue<'ctx> {
+        GameValue::new(left.context(), vec![GameValue::Number(left)], vec![GameValue::Number(right)])
+    }
+
+    /// Fails if any number in the form is born too late to look up
+    pub fn from_form(context: &'ctx SurrealContext, form: &GameForm) -> Result<GameValue<'ctx>, SurrealError> {
+        match form {
+            GameForm::Number(value) => Ok(GameValue::Number(Surreal::from_value(context, value)?)),
+            GameForm::Nimber(n) => Ok(GameValue::nimber(context, *n)),
+            GameForm::Options { left, right } => Ok(GameValue::new(
+                context,
+                left.iter().map(|l| GameValue::from_form(context, l)).collect::<Result<_, _>>()?,
+                right.iter().map(|r| GameValue::from_form(context, r)).collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+
+    pub fn context(&self) -> &'ctx SurrealContext {
+        match self {
+            GameValue::Number(number) | GameValue::NumberPlusNimber { number, .. } => number.context(),
+            GameValue::Game { context, .. } => context,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Surreal<'ctx>> {
+        match self {
+            GameValue::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// Which nimber this is, if it's one
+    pub fn as_nimber(&self) -> Option<usize> {
+        self.as_number_plus_nimber()
+            .filter(|(number, _)| *number == number.context().zero())
+            .map(|(_, nimber)| nimber)
+    }
+
+    /// Splits x + *n into x and n, if this is one
+    pub fn as_number_plus_nimber(&self) -> Option<(Surreal<'ctx>, usize)> {
+        match self {
+            GameValue::Number(number) => Some((*number, 0)),
+            GameValue::NumberPlusNimber { number, nimber } => Some((*number, *nimber)),
+            GameValue::Game { .. } => None,
+        }
+    }
+
+    /// The left and right options, expanding numbers into their canonical forms
+    pub fn options(&self) -> (Vec<GameValue<'ctx>>, Vec<GameValue<'ctx>>) {
+        match self {
+            GameValue::Number(number) => {
+                let (left, right) = number.options();
+                (
+                    left.into_iter().map(GameValue::Number).collect(),
+                    right.into_iter().map(GameValue::Number).collect(),
+                )
+            }
+            GameValue::NumberPlusNimber { number, nimber } => {
+                let options: Vec<_> = (0..*nimber).map(|n| GameValue::number_plus_nimber(*number, n)).collect();
+                (options.clone(), options)
+            }
+            GameValue::Game { left, right, .. } => (left.clone(), right.clone()),
+        }
+    }
+
+    /// An upper bound on the day this game is born, counting every option we're holding onto
+    pub fn birthday(&self) -> usize {
+        match self {
+            GameValue::Number(number) => number.birthday(),
+            GameValue::NumberPlusNimber { number, nimber } => number.birthday() + nimber,
+            GameValue::Game { left, right, .. } => {
+                1 + left.iter().chain(right).map(|option| option.birthday()).max().unwrap_or(0)
+            }
+        }
+    }
+
+    // G <= H unless some GL >= H or some HR <= G
+    fn less_or_equal(&self, other: &GameValue<'ctx>) -> bool {
+        // x + *n - (y + *m) is (x - y) + *(n xor m), and a number plus a nonzero nimber is only <= 0 when
+        // the number is negative
+        if let (Some((x, n)), Some((y, m))) = (self.as_number_plus_nimber(), other.as_number_plus_nimber()) {
+            return if n == m { x <= y } else { x < y };
+        }
+        let (self_left, _) = self.options();
+        let (_, other_right) = other.options();
+        !self_left.iter().any(|l| other.less_or_equal(l))
+            && !other_right.iter().any(|r| r.less_or_equal(self))
+    }
+}
+
+// x + *n comes out of `new` with exactly the options x, x + *, ..., x + *(n - 1) on both sides, since
+// nimbers don't dominate each other. This finds x and n from those options, if that's what they are.
+fn number_plus_nimber_options<'ctx>(left: &[GameValue<'ctx>], right: &[GameValue<'ctx>]) -> Option<(Surreal<'ctx>, usize)> {
+    let sorted = |options: &[GameValue<'ctx>]| {
+        let mut parts = options.iter().map(|o| o.as_number_plus_nimber()).collect::<Option<Vec<_>>>()?;
+        parts.sort_unstable_by_key(|&(_, nimber)| nimber);
+        Some(parts)
+    };
+    let left = sorted(left)?;
+    let number = left.first()?.0;
+    let n = left.len();
+    let is_nimber_sequence = left.iter().map(|&(_, nimber)| nimber).eq(0..n)
+        && left.iter().all(|&(x, _)| x == number);
+    (is_nimber_sequence && sorted(right)? == left).then_some((number, n))
+}
+
+// Drops every option that some other option is at least as good as, keeping one copy of equal options
+fn remove_dominated<'ctx>(
+    options: Vec<GameValue<'ctx>>,
+    worse_or_equal: impl Fn(&GameValue<'ctx>, &GameValue<'ctx>) -> bool,
+) -> Vec<GameValue<'ctx>> {
+    let mut kept: Vec<GameValue<'ctx>> = vec![];
+    for option in options {
+        if kept.iter().any(|k| worse_or_equal(&option, k)) {
+            continue;
+        }
+        kept.retain(|k| !worse_or_equal(k, &option));
+        kept.push(option);
+    }
+    kept
+}
+
+// A left option GL is reversible if it has a right option GLR <= G, since Right would always answer GL
+// with GLR. Then GL can be swapped for all the left options of GLR (and likewise for Right, with the
+// inequality flipped). `reversal` finds what to replace an option with, if it's reversible.
+fn bypass_reversible<'ctx>(
+    options: &[GameValue<'ctx>],
+    reversal: impl Fn(&GameValue<'ctx>) -> Option<Vec<GameValue<'ctx>>>,
+) -> (Vec<GameValue<'ctx>>, bool) {
+    let mut bypassed = false;
+    let mut result = vec![];
+    for option in options {
+        match reversal(option) {
+            Some(replacements) => {
+                bypassed = true;
+                result.extend(replacements);
+            }
+            None => result.push(option.clone()),
+        }
+    }
+    (result, bypassed)
+}
+
+// By the simplicity theorem, { L | R } equals the simplest number x with no L >= x and no R <= x, if there
+// is one. The numbers that work form an interval, so we can walk down the number tree looking for it,
+// and it's never born later than the game itself, which bounds how far we need to look.
+fn simplest_number<'ctx>(
+    context: &'ctx SurrealContext,
+    left: &[GameValue<'ctx>],
+    right: &[GameValue<'ctx>],
+) -> Option<Surreal<'ctx>> {
+    let numbers = |options: &[GameValue<'ctx>]| options.iter().map(|o| o.as_number()).collect::<Option<Vec<_>>>();
+    if let (Some(left), Some(right)) = (numbers(left), numbers(right)) {
+        return Surreal::new(context, &left, &right).ok();
+    }
+
+    let limit = 1 + left.iter().chain(right).map(|option| option.birthday()).max().unwrap_or(0);
+    let mut lower = vec![];
+    let mut upper = vec![];
+    let mut candidate = context.zero();
+    for _ in 0..=limit {
+        let value = GameValue::Number(candidate);
+        if left.iter().any(|l| value.less_or_equal(l)) {
+            lower = vec![candidate];
+        } else if right.iter().any(|r| r.less_or_equal(&value)) {
+            upper = vec![candidate];
+        } else {
+            return Some(candidate);
+        }
+        candidate = Surreal::new(context, &lower, &upper).ok()?;
+    }
+    None
+}
+
+impl<'ctx> From<Surreal<'ctx>> for GameValue<'ctx> {
+    fn from(number: Surreal<'ctx>) -> GameValue<'ctx> {
+        GameValue::Number(number)
+    }
+}
+
+// Games are only partially ordered, so two games can be incomparable (like * and 0)
+impl PartialOrd for GameValue<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.less_or_equal(other), other.less_or_equal(self)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+}
+
+// Equality is by value, not by form
+impl PartialEq for GameValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<'ctx> Neg for GameValue<'ctx> {
+    type Output = GameValue<'ctx>;
+
+    // -G = { -GR | -GL }
+    fn neg(self) -> GameValue<'ctx> {
+        match self {
+            GameValue::Number(number) => GameValue::Number(-number),
+            // Every nimber is its own negative
+            GameValue::NumberPlusNimber { number, nimber } => GameValue::number_plus_nimber(-number, nimber),
+            GameValue::Game { context, left, right } => GameValue::new(
+                context,
+                right.into_iter().map(|r| -r).collect(),
+                left.into_iter().map(|l| -l).collect(),
+            ),
+        }
+    }
+}
+
+impl<'ctx> GameValue<'ctx> {
+    /// Adds two games, failing if some number along the way is born too late to look up
+    ///
+    /// G + H = { GL + H, G + HL | GR + H, G + HR }
+    pub fn checked_add(self, other: GameValue<'ctx>) -> Result<GameValue<'ctx>, SurrealError> {
+        let context = self.context();
+        // Numbers add as numbers and nimbers add with xor
+        if let (Some((x, n)), Some((y, m))) = (self.as_number_plus_nimber(), other.as_number_plus_nimber()) {
+            return Ok(GameValue::number_plus_nimber(x.checked_add(y)?, n ^ m));
+        }
+        // Number translation: adding a number to a game that isn't one just shifts its options
+        if let Some(number) = self.as_number().or(other.as_number()) {
+            let game = if self.as_number().is_some() { other } else { self };
+            let (left, right) = game.options();
+            return Ok(GameValue::new(
+                context,
+                left.into_iter().map(|l| l.checked_add(GameValue::Number(number))).collect::<Result<_, _>>()?,
+                right.into_iter().map(|r| r.checked_add(GameValue::Number(number))).collect::<Result<_, _>>()?,
+            ));
+        }
+        let (self_left, self_right) = self.options();
+        let (other_left, other_right) = other.options();
+        Ok(GameValue::new(
+            context,
+            self_left.into_iter().map(|l| l.checked_add(other.clone()))
+                .chain(other_left.into_iter().map(|l| self.clone().checked_add(l)))
+                .collect::<Result<_, _>>()?,
+            self_right.into_iter().map(|r| r.checked_add(other.clone()))
+                .chain(other_right.into_iter().map(|r| self.clone().checked_add(r)))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+impl<'ctx> Add for GameValue<'ctx> {
+    type Output = GameValue<'ctx>;
+
+    fn add(self, other: GameValue<'ctx>) -> GameValue<'ctx> {
+        self.checked_add(other).expect("Sum is born too late to generate")
+    }
+}
+
+impl<'ctx> Sub for GameValue<'ctx> {
+    type Output = GameValue<'ctx>;
+
+    fn sub(self, other: GameValue<'ctx>) -> GameValue<'ctx> {
+        self + -other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(context: &SurrealContext, numerator: i64, exponent: u32) -> Surreal<'_> {
+        Surreal::from_dyadic(context, numerator, exponent).unwrap()
+    }
+
+    // Checks the options are exactly `expected`, in any order, comparing by value
+    fn assert_options(options: &[GameValue], expected: &[GameValue]) {
+        assert_eq!(options.len(), expected.len(), "{:?} should be {:?}", options, expected);
+        for option in expected {
+            assert!(options.contains(option), "{:?} is missing {:?}", options, option);
+        }
+    }
+
+    #[test]
+    fn up_reverses_through_star() {
+        let context = SurrealContext::new();
+        let (zero, up) = (GameValue::zero(&context), GameValue::up(&context));
+        // ↑'s right option * reverses out of the left side, leaving 0
+        let game = GameValue::new(&context, vec![up.clone()], vec![up.clone()]);
+        let (left, right) = game.options();
+        assert_options(&left, &[zero]);
+        assert_options(&right, &[up]);
+    }
+
+    #[test]
+    fn up_plus_star_is_canonical() {
+        let context = SurrealContext::new();
+        let (zero, star) = (GameValue::zero(&context), GameValue::star(&context));
+        let game = GameValue::up(&context) + star.clone();
+        let (left, right) = game.options();
+        assert_options(&left, &[zero.clone(), star]);
+        assert_options(&right, &[zero]);
+    }
+
+    #[test]
+    fn star_two_is_found() {
+        let context = SurrealContext::new();
+        let options = vec![GameValue::zero(&context), GameValue::star(&context)];
+        let game = GameValue::new(&context, options.clone(), options);
+        assert_eq!(game.as_nimber(), Some(2));
+    }
+
+    #[test]
+    fn games_minus_themselves_are_zero() {
+        let context = SurrealContext::new();
+        let games = [
+            GameValue::switch(number(&context, 1, 0), number(&context, -1, 0)),
+            GameValue::switch(number(&context, 2, 0), number(&context, 1, 1)),
+            GameValue::switch(number(&context, 0, 0), number(&context, -3, 0)),
+            GameValue::up(&context) + GameValue::star(&context),
+        ];
+        for game in games {
+            let difference = game.clone() - game.clone();
+            assert_eq!(difference.as_number(), Some(context.zero()), "{:?} - itself is {:?}", game, difference);
+        }
+    }
+
+    // Large nimbers add, compare and combine with numbers through their parts alone
+    #[test]
+    fn large_nimbers_are_cheap() {
+        let context = SurrealContext::new();
+        let (zero, one) = (context.zero(), number(&context, 1, 0));
+        let big = GameValue::nimber(&context, 40);
+        assert_eq!(big.as_nimber(), Some(40));
+        assert_eq!(big.clone() + GameValue::nimber(&context, 40), GameValue::zero(&context));
+        assert_eq!((GameValue::nimber(&context, 37) + GameValue::nimber(&context, 21)).as_nimber(), Some(37 ^ 21));
+        assert_eq!(big.partial_cmp(&GameValue::zero(&context)), None);
+        assert!(GameValue::number_plus_nimber(one, 40) > big);
+        assert!(GameValue::number_plus_nimber(-one, 3) < GameValue::number_plus_nimber(zero, 40));
+        assert_eq!((GameValue::from(one) + big).as_number_plus_nimber(), Some((one, 40)));
+    }
+}
@@ -1,18 +1,21 @@
-use crate::computer::Position;
+use std::collections::{BTreeSet, HashSet};
 use itertools::Itertools;
-use nannou::color::{Rgba8, Srgb, BLUE, CYAN, PINK, RED};
-use petgraph::data::DataMap;
+use nannou::color::{Rgba8, Srgb, BLUE, CYAN, GREEN, LIGHTGREEN, PINK, RED};
 use petgraph::prelude::*;
+use petgraph::visit::IntoEdgeReferences;
 use rand::distributions::Standard;
 use rand::prelude::*;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
-
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum Color {
     Red,
     Blue,
+    /// Either player can cut green edges
+    Green,
 }
 
+// Only red and blue, since random positions are for Red-Blue Hackenbush
 impl Distribution<Color> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Color {
         match rng.gen_range(0..2) {
@@ -28,6 +31,7 @@ impl Color {
         match self {
             Color::Red => RED,
             Color::Blue => BLUE,
+            Color::Green => GREEN,
         }
     }
 
@@ -35,6 +39,7 @@ impl Color {
         let mut color: Rgba8 = match self {
             Color::Red => RED,
             Color::Blue => BLUE,
+            Color::Green => GREEN,
         }
         .into();
         color.alpha = 128;
@@ -45,24 +50,43 @@ impl Color {
         match self {
             Color::Red => PINK,
             Color::Blue => CYAN,
+            Color::Green => LIGHTGREEN,
         }
     }
 
+    // Green belongs to both players, so it stays green
     pub fn invert(&self) -> Color {
         match self {
             Color::Blue => Color::Red,
             Color::Red => Color::Blue,
+            Color::Green => Color::Green,
         }
     }
+
+    /// Whether `player` is allowed to cut an edge of this color
+    pub fn playable_by(&self, player: Color) -> bool {
+        *self == player || *self == Color::Green
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Game {
     graph: StableUnGraph<(bool, (f32, f32)), Color>,
     turn: Color,
+    grounds: BTreeSet<NodeIndex>, // Every node that sits on the ground
 }
 
 impl Game {
+    /// Any number of nodes can be on the ground. They all count as the same ground, so an edge between two of
+    /// them stays up until it's cut.
+    pub fn with_grounds(
+        graph: StableUnGraph<(bool, (f32, f32)), Color>,
+        turn: Color,
+        grounds: impl IntoIterator<Item = NodeIndex>,
+    ) -> Game {
+        Game { graph, turn, grounds: grounds.into_iter().collect() }
+    }
+
     pub fn random_triangles(size: usize, rng: &mut ThreadRng) -> Game {
         let mut current_color: Color = rng.gen();
 
@@ -112,15 +136,16 @@ impl Game {
             .unwrap()
             .1;
 
-        for i in 1..points.len() {
-            let (x, y) = points[i];
-            points[i] = (x, y - lowest + 0.3);
-            *graph.node_weight_mut(NodeIndex::new(i)).unwrap() = (false, points[i]);
+        for (i, point) in points.iter_mut().enumerate().skip(1) {
+            let (x, y) = *point;
+            *point = (x, y - lowest + 0.3);
+            *graph.node_weight_mut(NodeIndex::new(i)).unwrap() = (false, *point);
         }
 
         Game {
             graph,
             turn: current_color.invert(),
+            grounds: BTreeSet::from([NodeIndex::new(0)]),
         }
     }
 
@@ -128,11 +153,21 @@ impl Game {
         let mut new_state = (*self).clone();
         new_state.turn = new_state.turn.invert();
         new_state.graph.remove_edge(target);
-        let base = new_state.graph.node_weight(NodeIndex::new(0)).unwrap().0;
-        let mut stack = vec![NodeIndex::new(0)];
+        new_state.remove_floating();
+        new_state
+    }
+
+    /// Removes every node and edge that's no longer connected to the ground
+    pub fn remove_floating(&mut self) {
+        let base = self.tag();
+        // Everything still attached to some part of the ground stays
+        let mut stack = self.grounds.iter().copied().collect_vec();
+        for &ground in &stack {
+            self.graph.node_weight_mut(ground).unwrap().0 = !base;
+        }
         while let Some(node) = stack.pop() {
-            for neighbor in new_state.graph.neighbors(node).collect_vec().clone() {
-                let tag = new_state
+            for neighbor in self.graph.neighbors(node).collect_vec().clone() {
+                let tag = self
                     .graph
                     .node_weight_mut(NodeIndex::new(neighbor.index()))
                     .unwrap();
@@ -143,27 +178,106 @@ impl Game {
             }
         }
 
-        for edge in new_state.graph.edge_indices().collect_vec().clone() {
-            let (a, b) = new_state.graph.edge_endpoints(edge).unwrap();
-            if new_state.graph.node_weight(a).unwrap().0 == base
-                && new_state.graph.node_weight(b).unwrap().0 == base
+        for edge in self.graph.edge_indices().collect_vec().clone() {
+            let (a, b) = self.graph.edge_endpoints(edge).unwrap();
+            if self.graph.node_weight(a).unwrap().0 == base
+                && self.graph.node_weight(b).unwrap().0 == base
             {
-                new_state.graph.remove_edge(edge);
+                self.graph.remove_edge(edge);
             }
         }
 
-        for node in new_state.graph.node_indices().collect_vec().clone() {
-            if new_state.graph.node_weight(node).unwrap().0 == base {
-                new_state.graph.remove_node(node);
+        for node in self.graph.node_indices().collect_vec().clone() {
+            if self.graph.node_weight(node).unwrap().0 == base {
+                self.graph.remove_node(node);
             }
         }
+    }
 
-        new_state
+    /// Splits the position into pieces that only touch at the ground. The position is the sum of these
+    /// games, and each one keeps the original node and edge indexes.
+    pub fn components(&self) -> Vec<Game> {
+        let mut seen = self.grounds.iter().copied().collect::<HashSet<_>>();
+        let mut components = vec![];
+        for start in self.graph.node_indices() {
+            if !seen.insert(start) {
+                continue;
+            }
+            // Flood fill without going through the ground
+            let mut members = HashSet::from([start]);
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                for neighbor in self.graph.neighbors(node) {
+                    if self.is_ground(neighbor) {
+                        members.insert(neighbor);
+                    } else if seen.insert(neighbor) {
+                        members.insert(neighbor);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+
+            let mut component = self.clone();
+            component.graph.retain_nodes(|_, node| members.contains(&node));
+            component.graph.retain_edges(|graph, edge| {
+                let (a, b) = graph.edge_endpoints(edge).unwrap();
+                !(self.is_ground(a) && self.is_ground(b))
+            });
+            component.grounds.retain(|node| members.contains(node));
+            components.push(component);
+        }
+
+        // Edges that run along the ground don't touch anything else, so each one is a game of its own
+        let ground_edges = self.graph.edge_references()
+            .filter(|edge| self.is_ground(edge.source()) && self.is_ground(edge.target()))
+            .map(|edge| (edge.id(), edge.source(), edge.target()))
+            .collect_vec();
+        for (ground_edge, a, b) in ground_edges {
+            let mut component = self.clone();
+            component.graph.retain_nodes(|_, node| node == a || node == b);
+            component.graph.retain_edges(|_, edge| edge == ground_edge);
+            component.grounds.retain(|&node| node == a || node == b);
+            components.push(component);
+        }
+        components
+    }
+
+    /// The same position with all of its ground nodes merged into one, which is then the only ground.
+    /// Edges between ground nodes become loops.
+    pub fn fuse_grounds(&self) -> Game {
+        let mut fused = self.clone();
+        let Some(&ground) = self.grounds.first() else {
+            return fused;
+        };
+        let merged = |node| if self.is_ground(node) { ground } else { node };
+        for edge in self.graph.edge_references() {
+            let (a, b) = (merged(edge.source()), merged(edge.target()));
+            if (a, b) != (edge.source(), edge.target()) {
+                fused.graph.remove_edge(edge.id());
+                fused.graph.add_edge(a, b, *edge.weight());
+            }
+        }
+        for &node in self.grounds.iter().skip(1) {
+            fused.graph.remove_node(node);
+        }
+        fused.grounds = BTreeSet::from([ground]);
+        fused
+    }
+
+    /// Adds a node that isn't joined to anything yet
+    pub fn add_node(&mut self, position: (f32, f32)) -> NodeIndex {
+        let tag = self.tag();
+        self.graph.add_node((tag, position))
+    }
+
+    /// Puts `node` on the ground
+    pub fn add_ground(&mut self, node: NodeIndex) {
+        self.grounds.insert(node);
     }
 
     pub fn add_branch(&self, target: NodeIndex, position: (f32, f32)) -> Game {
         let mut new_state = (*self).clone();
-        let new_node = new_state.graph.add_node((false, position));
+        let new_node = new_state.add_node(position);
         new_state.graph.add_edge(target, new_node, self.turn);
         new_state
     }
@@ -176,6 +290,14 @@ impl Game {
         &mut self.graph
     }
 
+    pub fn grounds(&self) -> &BTreeSet<NodeIndex> {
+        &self.grounds
+    }
+
+    pub fn is_ground(&self, node: NodeIndex) -> bool {
+        self.grounds.contains(&node)
+    }
+
     pub fn get_turn(&self) -> Color {
         self.turn
     }
@@ -183,4 +305,9 @@ impl Game {
     pub fn switch_turn(&mut self) {
         self.turn = self.turn.invert();
     }
+
+    // The mark that `make_move` flips on every node it keeps. All the nodes share it between moves.
+    fn tag(&self) -> bool {
+        self.graph.node_weights().next().is_some_and(|&(tag, _)| tag)
+    }
 }
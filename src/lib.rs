@@ -0,0 +1,10 @@
+pub mod app;
+pub mod computer;
+pub mod dyadic;
+pub mod games;
+pub mod hackenbush;
+pub mod nimbers;
+pub mod notation;
+pub mod save;
+pub mod surreals;
+pub mod transfinite;
@@ -1,9 +1,4 @@
-mod app;
-mod computer;
-mod hackenbush;
-mod surreals;
-
-use nannou::prelude::*;
+use surreal_numbers::app;
 
 fn main() {
     nannou::app(app::model).event(app::event).run();
@@ -0,0 +1,287 @@
+//! Nimbers, the values of impartial games, and a Sprague-Grundy evaluator for Green Hackenbush.
+//!
+//! Any position where both players have the same moves is equal to some nimber *n. Nimbers add with XOR,
+//! and multiply with nim-multiplication, which together make them a field.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use crate::games::GameValue;
+use crate::hackenbush::{Color, Game};
+use crate::surreals::SurrealContext;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Nimber(u64);
+
+impl Nimber {
+    pub fn new(value: u64) -> Nimber {
+        Nimber(value)
+    }
+
+    pub fn zero() -> Nimber {
+        Nimber(0)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// The smallest nimber that isn't in `options`, which is the value of a game with those options
+    pub fn mex(options: impl IntoIterator<Item = Nimber>) -> Nimber {
+        let options: HashSet<_> = options.into_iter().collect();
+        (0..).map(Nimber).find(|n| !options.contains(n)).unwrap()
+    }
+
+    pub fn to_game(self, context: &SurrealContext) -> GameValue<'_> {
+        GameValue::nimber(context, self.0 as usize)
+    }
+}
+
+impl Add for Nimber {
+    type Output = Nimber;
+
+    // Nim addition really is XOR
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Nimber) -> Nimber {
+        Nimber(self.0 ^ other.0)
+    }
+}
+
+// Every nimber is its own negative
+impl Neg for Nimber {
+    type Output = Nimber;
+
+    fn neg(self) -> Nimber {
+        self
+    }
+}
+
+impl Sub for Nimber {
+    type Output = Nimber;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: Nimber) -> Nimber {
+        self + other
+    }
+}
+
+impl Mul for Nimber {
+    type Output = Nimber;
+
+    fn mul(self, other: Nimber) -> Nimber {
+        Nimber(nim_multiply(self.0, other.0, 64))
+    }
+}
+
+// Splits both numbers in half around the Fermat 2-power F = 2^half, where F * F = F + F/2 in nim
+// arithmetic. Then (aF + b)(cF + d) = (ac + ad + bc)F + ac(F/2) + bd, and the middle term can be found
+// with one multiplication as (a + b)(c + d) - bd.
+fn nim_multiply(x: u64, y: u64, bits: u32) -> u64 {
+    if x < 2 || y < 2 {
+        return x * y;
+    }
+    let half = bits / 2;
+    let mask = (1 << half) - 1;
+    let (a, b) = (x >> half, x & mask);
+    let (c, d) = (y >> half, y & mask);
+
+    let high_product = nim_multiply(a, c, half);
+    let low_product = nim_multiply(b, d, half);
+    let high = nim_multiply(a ^ b, c ^ d, half) ^ low_product;
+    let low = low_product ^ nim_multiply(high_product, 1 << (half - 1), half);
+    (high << half) | low
+}
+
+impl fmt::Display for Nimber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "0"),
+            1 => write!(f, "*"),
+            n => write!(f, "*{}", n),
+        }
+    }
+}
+
+/// The Sprague-Grundy value of a Green Hackenbush position, or `None` if any edge isn't green.
+///
+/// By the fusion principle, the nodes of any cycle can be merged into one without changing the value,
+/// which turns the cycle's edges into loops, and a loop is worth the same as a single edge sticking up.
+/// That leaves a tree, which the colon principle collapses from the top down. The ground is fused first,
+/// since a path between two ground nodes is a cycle too.
+pub fn sprague_grundy(game: &Game) -> Option<Nimber> {
+    if game.get_graph().edge_weights().any(|&color| color != Color::Green) {
+        return None;
+    }
+    let game = &game.fuse_grounds();
+    let graph = game.get_graph();
+    let Some(&ground) = game.grounds().first() else {
+        return Some(Nimber::zero());
+    };
+
+    // Edges that aren't bridges are on a cycle, so everything they join gets fused together
+    let bridges = find_bridges(game);
+    let mut fused: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    for start in graph.node_indices() {
+        if fused.contains_key(&start) {
+            continue;
+        }
+        fused.insert(start, start);
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for edge in graph.edges(node) {
+                let next = other_end(edge.source(), edge.target(), node);
+                if !bridges.contains(&edge.id()) && !fused.contains_key(&next) {
+                    fused.insert(next, start);
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    let mut loops: HashMap<NodeIndex, usize> = HashMap::new();
+    let mut tree: HashMap<NodeIndex, Vec<(EdgeIndex, NodeIndex)>> = HashMap::new();
+    for edge in graph.edge_references() {
+        let (a, b) = (fused[&edge.source()], fused[&edge.target()]);
+        if a == b {
+            *loops.entry(a).or_default() += 1;
+        } else {
+            tree.entry(a).or_default().push((edge.id(), b));
+            tree.entry(b).or_default().push((edge.id(), a));
+        }
+    }
+
+    Some(colon_principle(fused[&ground], None, &tree, &loops))
+}
+
+// Each branch above a node can be swapped for a stalk worth 1 + the branch's value, and each loop for a
+// stalk of length 1, so the node is worth the nim sum of all of them
+fn colon_principle(
+    node: NodeIndex,
+    from: Option<EdgeIndex>,
+    tree: &HashMap<NodeIndex, Vec<(EdgeIndex, NodeIndex)>>,
+    loops: &HashMap<NodeIndex, usize>,
+) -> Nimber {
+    let loop_value = Nimber((loops.get(&node).copied().unwrap_or(0) % 2) as u64);
+    tree.get(&node).into_iter().flatten()
+        .filter(|&&(edge, _)| Some(edge) != from)
+        // A stalk one longer, which isn't the same as nim adding *1
+        .map(|&(edge, next)| Nimber(colon_principle(next, Some(edge), tree, loops).0 + 1))
+        .fold(loop_value, |total, branch| total + branch)
+}
+
+// Tarjan's bridge finding. Edges are tracked by index rather than by endpoints, so doubled edges between
+// two nodes count as a cycle.
+fn find_bridges(game: &Game) -> HashSet<EdgeIndex> {
+    struct Search<'a> {
+        game: &'a Game,
+        discovered: HashMap<NodeIndex, usize>,
+        low: HashMap<NodeIndex, usize>,
+        bridges: HashSet<EdgeIndex>,
+    }
+
+    impl Search<'_> {
+        fn visit(&mut self, node: NodeIndex, from: Option<EdgeIndex>) {
+            let time = self.discovered.len();
+            self.discovered.insert(node, time);
+            self.low.insert(node, time);
+            for edge in self.game.get_graph().edges(node) {
+                if Some(edge.id()) == from {
+                    continue;
+                }
+                let next = other_end(edge.source(), edge.target(), node);
+                match self.discovered.get(&next) {
+                    Some(&seen) => {
+                        let low = self.low[&node].min(seen);
+                        self.low.insert(node, low);
+                    }
+                    None => {
+                        self.visit(next, Some(edge.id()));
+                        let low = self.low[&node].min(self.low[&next]);
+                        self.low.insert(node, low);
+                        if self.low[&next] > self.discovered[&node] {
+                            self.bridges.insert(edge.id());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut search = Search { game, discovered: HashMap::new(), low: HashMap::new(), bridges: HashSet::new() };
+    for node in game.get_graph().node_indices() {
+        if !search.discovered.contains_key(&node) {
+            search.visit(node, None);
+        }
+    }
+    search.bridges
+}
+
+fn other_end(source: NodeIndex, target: NodeIndex, node: NodeIndex) -> NodeIndex {
+    if source == node { target } else { source }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(text: &str) -> Game {
+        Game::from_reader(text.as_bytes()).unwrap()
+    }
+
+    // The value straight from the definition, trying every move
+    fn search(game: &Game) -> Nimber {
+        Nimber::mex(game.get_graph().edge_indices().map(|edge| search(&game.make_move(edge))))
+    }
+
+    fn assert_value(text: &str, expected: u64) {
+        let game = game(text);
+        assert_eq!(sprague_grundy(&game), Some(Nimber(expected)), "evaluating {}", text);
+        assert_eq!(search(&game), Nimber(expected), "searching {}", text);
+    }
+
+    #[test]
+    fn nim_products() {
+        assert_eq!(Nimber(2) * Nimber(2), Nimber(3));
+        assert_eq!(Nimber(4) * Nimber(4), Nimber(6));
+        assert_eq!(Nimber(16) * Nimber(16), Nimber(24));
+        assert_eq!(Nimber(3) * Nimber(1), Nimber(3));
+        assert_eq!(Nimber(5) * Nimber(0), Nimber(0));
+        assert_eq!(Nimber(7) + Nimber(5), Nimber(2));
+    }
+
+    #[test]
+    fn stalks_and_trees() {
+        assert_value("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 0 2\nnode 3 0 3\nedge 0 1 green\nedge 1 2 green\nedge 2 3 green", 3);
+        // A fork of two edges on top of a stalk is a stalk of 1 + (1 xor 1) = 1 edge
+        assert_value("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 -1 2\nnode 3 1 2\nedge 0 1 green\nedge 1 2 green\nedge 1 3 green", 1);
+    }
+
+    #[test]
+    fn cycles_fuse_into_loops() {
+        // A triangle fuses into three loops, and an odd number of loops is *
+        assert_value("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 1 1\nedge 0 1 green\nedge 1 2 green\nedge 2 0 green", 1);
+        // A stalk of two on top of a triangle
+        assert_value("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 1 1\nnode 3 0 2\nnode 4 0 3\nedge 0 1 green\nedge 1 2 green\nedge 2 0 green\nedge 1 3 green\nedge 3 4 green", 3);
+    }
+
+    #[test]
+    fn doubled_edges_are_a_cycle() {
+        assert_value("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nedge 0 1 green\nedge 0 1 green", 0);
+        assert_value("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nnode 2 0 2\nedge 0 1 green\nedge 0 1 green\nedge 1 2 green", 1);
+    }
+
+    #[test]
+    fn grounds_fuse_together() {
+        // An arch between two ground nodes is a cycle through the ground
+        assert_value("turn blue\nground 0 2\nnode 0 0 0\nnode 1 1 1\nnode 2 2 0\nedge 0 1 green\nedge 1 2 green", 0);
+        assert_value("turn blue\nground 0 2\nnode 0 0 0\nnode 1 1 1\nnode 2 2 0\nedge 0 1 green\nedge 1 2 green\nedge 0 2 green", 1);
+    }
+
+    #[test]
+    fn red_and_blue_edges_are_skipped() {
+        let game = game("turn blue\nground 0\nnode 0 0 0\nnode 1 0 1\nedge 0 1 green\nedge 0 1 blue");
+        assert_eq!(sprague_grundy(&game), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Reading and writing games in Conway's { L | R } notation. Options are separated by commas, and numbers
+//! can be written in shorthand like `1/2` or `-3` instead of spelling out their canonical forms. Stars
+//! and ups have shorthand too: `*`, `*3`, `↑` and `↓`, and games are printed as things like `1/2*` where
+//! they can be.
+//!
+//! Printing uses shorthand for options by default, while the alternate flag (`{:#}`) spells out every
+//! option in full, all the way down to `{|}`.
+
+use std::fmt;
+use std::str::FromStr;
+use crate::dyadic::Dyadic;
+use crate::games::{GameForm, GameValue};
+use crate::surreals::{Surreal, SurrealContext, SurrealError, SURREALS};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedEnd,
+    UnexpectedCharacter { position: usize, found: char },
+    /// A number that isn't an integer or a fraction over a power of two
+    InvalidNumber(String),
+    /// The form parsed fine, but isn't a number, like { 1 | 0 }
+    NotANumber,
+    /// A number too far from zero or too fine to look up, like 1000
+    BornTooLate,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            ParseError::UnexpectedCharacter { position, found } => {
+                write!(f, "unexpected character '{}' at position {}", found, position)
+            }
+            ParseError::InvalidNumber(number) => write!(f, "'{}' is not a dyadic number", number),
+            ParseError::NotANumber => write!(f, "form is not a number"),
+            ParseError::BornTooLate => write!(f, "number is born too late to generate"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<SurrealError> for ParseError {
+    fn from(error: SurrealError) -> ParseError {
+        match error {
+            SurrealError::BornTooLate => ParseError::BornTooLate,
+            _ => ParseError::NotANumber,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Parser<'a> {
+        Parser { text, position: 0 }
+    }
+
+    // Skips whitespace, then looks at the next character without consuming it
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+        self.text[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(ParseError::UnexpectedCharacter { position: self.position, found: c }),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) => Err(ParseError::UnexpectedCharacter { position: self.position, found: c }),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_game(&mut self) -> Result<GameForm, ParseError> {
+        match self.peek() {
+            Some('{') => {
+                self.expect('{')?;
+                let left = self.parse_list('|')?;
+                self.expect('|')?;
+                let right = self.parse_list('}')?;
+                self.expect('}')?;
+                Ok(GameForm::new(left, right))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(GameForm::Number(self.parse_number()?)),
+            Some('*') => {
+                self.expect('*')?;
+                let rest = &self.text[self.position..];
+                let length = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                self.position += length;
+                let n = if length == 0 { 1 } else {
+                    rest[..length].parse().map_err(|_| ParseError::InvalidNumber(rest[..length].to_string()))?
+                };
+                Ok(GameForm::nimber(n))
+            }
+            Some('↑') => {
+                self.expect('↑')?;
+                Ok(GameForm::up())
+            }
+            Some('↓') => {
+                self.expect('↓')?;
+                Ok(GameForm::down())
+            }
+            Some(c) => Err(ParseError::UnexpectedCharacter { position: self.position, found: c }),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    fn parse_list(&mut self, end: char) -> Result<Vec<GameForm>, ParseError> {
+        let mut games = vec![];
+        if self.peek() == Some(end) {
+            return Ok(games);
+        }
+        loop {
+            games.push(self.parse_game()?);
+            if self.peek() != Some(',') {
+                return Ok(games);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Dyadic, ParseError> {
+        let rest = &self.text[self.position..];
+        let length = rest.find(|c: char| !(c == '-' || c == '/' || c.is_ascii_digit())).unwrap_or(rest.len());
+        self.position += length;
+        rest[..length].parse()
+    }
+}
+
+impl FromStr for GameForm {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<GameForm, ParseError> {
+        let mut parser = Parser::new(text);
+        let game = parser.parse_game()?;
+        parser.finish()?;
+        Ok(game)
+    }
+}
+
+impl<'ctx> Surreal<'ctx> {
+    /// Parses a number written in brace notation or shorthand, like `{0, 1/2 | 3}` or `-3/8`
+    pub fn parse(context: &'ctx SurrealContext, text: &str) -> Result<Surreal<'ctx>, ParseError> {
+        Ok(text.parse::<GameForm>()?.to_surreal(context)?)
+    }
+}
+
+impl FromStr for Surreal<'static> {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Surreal<'static>, ParseError> {
+        Surreal::parse(&SURREALS, text)
+    }
+}
+
+// Writes { left | right }, passing the alternate flag down to the options
+fn write_options<T: fmt::Display>(f: &mut fmt::Formatter<'_>, left: &[T], right: &[T]) -> fmt::Result {
+    let write_list = |f: &mut fmt::Formatter<'_>, options: &[T]| -> fmt::Result {
+        for (i, option) in options.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if f.alternate() {
+                write!(f, "{:#}", option)?;
+            } else {
+                write!(f, "{}", option)?;
+            }
+        }
+        Ok(())
+    };
+
+    write!(f, "{{")?;
+    write_list(f, left)?;
+    if !left.is_empty() {
+        write!(f, " ")?;
+    }
+    write!(f, "|")?;
+    if !right.is_empty() {
+        write!(f, " ")?;
+    }
+    write_list(f, right)?;
+    write!(f, "}}")
+}
+
+impl fmt::Display for GameForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameForm::Number(value) if !f.alternate() => write!(f, "{}", value),
+            GameForm::Nimber(1) if !f.alternate() => write!(f, "*"),
+            GameForm::Nimber(n) if !f.alternate() => write!(f, "*{}", n),
+            _ => {
+                let (left, right) = self.options();
+                write_options(f, &left, &right)
+            }
+        }
+    }
+}
+
+impl<'ctx> GameValue<'ctx> {
+    /// Parses any short game, like `{1 | -1}`, `*2` or `{0 | ↑}`
+    pub fn parse(context: &'ctx SurrealContext, text: &str) -> Result<GameValue<'ctx>, ParseError> {
+        Ok(GameValue::from_form(context, &text.parse::<GameForm>()?)?)
+    }
+}
+
+impl fmt::Display for GameValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let GameValue::Number(number) = self {
+            return write!(f, "{}", number.to_dyadic());
+        }
+        if let Some((number, nimber)) = self.as_number_plus_nimber() {
+            if number != number.context().zero() {
+                write!(f, "{}", number.to_dyadic())?;
+            }
+            return match nimber {
+                1 => write!(f, "*"),
+                n => write!(f, "*{}", n),
+            };
+        }
+        let (left, right) = self.options();
+        let is = |options: &[GameValue<'_>], nimber: usize| options.len() == 1 && options[0].as_nimber() == Some(nimber);
+        if is(&left, 0) && is(&right, 1) {
+            write!(f, "↑")
+        } else if is(&left, 1) && is(&right, 0) {
+            write!(f, "↓")
+        } else {
+            write_options(f, &left, &right)
+        }
+    }
+}
+
+impl fmt::Display for Surreal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (left, right) = self.options();
+        if f.alternate() {
+            write_options(f, &left, &right)
+        } else {
+            let left: Vec<_> = left.iter().map(|l| l.to_dyadic()).collect();
+            let right: Vec<_> = right.iter().map(|r| r.to_dyadic()).collect();
+            write_options(f, &left, &right)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forms_round_trip() {
+        for text in ["{0, 1 | 2}", "{{|} |}", "-3/8", "{1 | -1}", "{0 | {{|} | {{|} | {|}}}}", "{0, * | *2}"] {
+            let form: GameForm = text.parse().unwrap();
+            assert_eq!(form.to_string(), text);
+            // Spelling everything out has to parse back to the same game
+            let spelled_out = format!("{:#}", form);
+            assert_eq!(format!("{:#}", spelled_out.parse::<GameForm>().unwrap()), spelled_out);
+        }
+    }
+
+    // *n parses to the shorthand form, which prints back the same way and expands one level at a time
+    #[test]
+    fn large_nimbers_parse() {
+        let form: GameForm = "*20".parse().unwrap();
+        assert_eq!(form, GameForm::nimber(20));
+        assert_eq!(form.to_string(), "*20");
+        assert_eq!(form.options().0.len(), 20);
+    }
+
+    #[test]
+    fn numbers_parse_to_their_values() {
+        let context = SurrealContext::new();
+        assert_eq!(Surreal::parse(&context, "{ {|} | }").unwrap().to_dyadic(), Dyadic::from(1));
+        assert_eq!(Surreal::parse(&context, "-3/8").unwrap().to_dyadic(), Dyadic::new(-3, 3));
+        assert_eq!(Surreal::parse(&context, "{0, 1 | 2}").unwrap().to_dyadic(), Dyadic::new(3, 1));
+        assert_eq!(Surreal::parse(&context, "-3/8").unwrap().to_string(), "{-1/2 | -1/4}");
+    }
+
+    #[test]
+    fn games_print_in_shorthand() {
+        let context = SurrealContext::new();
+        for text in ["*3", "{0 | ↑}", "{1 | -1}", "3/2", "↓", "*"] {
+            assert_eq!(GameValue::parse(&context, text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn bad_input_is_rejected() {
+        let context = SurrealContext::new();
+        assert_eq!("1/3".parse::<GameForm>(), Err(ParseError::InvalidNumber("1/3".to_string())));
+        assert_eq!(Surreal::parse(&context, "{1|0}"), Err(ParseError::NotANumber));
+        assert_eq!("{0 |".parse::<GameForm>(), Err(ParseError::UnexpectedEnd));
+        assert_eq!("{0 | 1}x".parse::<GameForm>(), Err(ParseError::UnexpectedCharacter { position: 7, found: 'x' }));
+    }
+}
@@ -0,0 +1,382 @@
+//! A plain text format for saving Hackenbush positions, meant to be easy to write and edit by hand:
+//!
+//! ```text
+//! # A blue stalk of two edges, with a red edge next to it
+//! turn blue
+//! ground 0
+//! node 0 0 0
+//! node 1 0 1
+//! node 2 0 2
+//! node 3 1 1
+//! edge 0 1 blue
+//! edge 1 2 blue
+//! edge 0 3 red
+//! ```
+//!
+//! Each line is one entry, and blank lines and lines starting with `#` are ignored. `node <id> <x> <y>`
+//! places a node, where ids are any whole numbers as long as each is only used once. `edge <id> <id>
+//! <color>` joins two nodes with a `red`, `blue` or `green` edge. `ground <id>...` says which nodes are on
+//! the ground, and `turn <color>` says whose turn it is. Entries can come in any order, and anything that
+//! isn't connected to the ground is dropped.
+//!
+//! Positions can also be written as Graphviz DOT for drawing, and with the `serde` feature, as JSON with
+//! the same fields as the text format.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use itertools::Itertools;
+use petgraph::graph::NodeIndex;
+use petgraph::stable_graph::StableUnGraph;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use crate::hackenbush::{Color, Game};
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// A line that couldn't be understood, numbered from 1
+    InvalidLine { line: usize, text: String },
+    /// An edge or the ground refers to a node that was never given
+    UnknownNode(u64),
+    DuplicateNode(u64),
+    MissingGround,
+    MissingTurn,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "couldn't read position: {}", error),
+            LoadError::InvalidLine { line, text } => write!(f, "couldn't understand line {}: '{}'", line, text),
+            LoadError::UnknownNode(id) => write!(f, "node {} is used but never placed", id),
+            LoadError::DuplicateNode(id) => write!(f, "node {} is placed more than once", id),
+            LoadError::MissingGround => write!(f, "no ground node given"),
+            LoadError::MissingTurn => write!(f, "no turn given"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> LoadError {
+        LoadError::Io(error)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::Red => write!(f, "red"),
+            Color::Blue => write!(f, "blue"),
+            Color::Green => write!(f, "green"),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Color, ()> {
+        match text {
+            "red" => Ok(Color::Red),
+            "blue" => Ok(Color::Blue),
+            "green" => Ok(Color::Green),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Game {
+    /// Writes the position in the text format described above. Nodes are renumbered from 0.
+    pub fn to_writer(&self, mut writer: impl Write) -> io::Result<()> {
+        let graph = self.get_graph();
+        let ids = node_ids(self);
+
+        writeln!(writer, "turn {}", self.get_turn())?;
+        writeln!(writer, "ground {}", self.grounds().iter().map(|node| ids[node]).join(" "))?;
+        for node in graph.node_indices() {
+            let (_, (x, y)) = graph[node];
+            writeln!(writer, "node {} {} {}", ids[&node], x, y)?;
+        }
+        for edge in graph.edge_references() {
+            writeln!(writer, "edge {} {} {}", ids[&edge.source()], ids[&edge.target()], edge.weight())?;
+        }
+        writer.flush()
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Game, LoadError> {
+        let mut turn = None;
+        let mut grounds: Vec<u64> = vec![];
+        let mut nodes: Vec<(u64, (f32, f32))> = vec![];
+        let mut edges: Vec<(u64, u64, Color)> = vec![];
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let words: Vec<_> = line.split_whitespace().collect();
+            let invalid = || LoadError::InvalidLine { line: number + 1, text: line.clone() };
+            match words[..] {
+                [] => {}
+                [first, ..] if first.starts_with('#') => {}
+                ["turn", color] => match color.parse() {
+                    Ok(color @ (Color::Red | Color::Blue)) => turn = Some(color),
+                    _ => return Err(invalid()),
+                },
+                ["ground", ref ids @ ..] if !ids.is_empty() => {
+                    for id in ids {
+                        grounds.push(id.parse().map_err(|_| invalid())?);
+                    }
+                }
+                ["node", id, x, y] => {
+                    let id = id.parse().map_err(|_| invalid())?;
+                    // Parsing accepts NaN and infinities, which can't be drawn
+                    let coordinate = |text: &str| text.parse::<f32>().ok().filter(|c| c.is_finite()).ok_or_else(invalid);
+                    nodes.push((id, (coordinate(x)?, coordinate(y)?)));
+                }
+                ["edge", a, b, color] => edges.push((
+                    a.parse().map_err(|_| invalid())?,
+                    b.parse().map_err(|_| invalid())?,
+                    color.parse().map_err(|_| invalid())?,
+                )),
+                _ => return Err(invalid()),
+            }
+        }
+        let turn = turn.ok_or(LoadError::MissingTurn)?;
+        build_game(turn, grounds, nodes, edges)
+    }
+
+    /// Writes the position as a Graphviz graph, with each node pinned where it's drawn in the app
+    pub fn to_dot(&self, mut writer: impl Write) -> io::Result<()> {
+        let graph = self.get_graph();
+        let ids = node_ids(self);
+
+        writeln!(writer, "graph hackenbush {{")?;
+        writeln!(writer, "    label=\"{} to move\";", self.get_turn())?;
+        for node in graph.node_indices() {
+            let (_, (x, y)) = graph[node];
+            if self.is_ground(node) {
+                writeln!(writer, "    {} [label=\"ground\", shape=box, pos=\"{},{}!\"];", ids[&node], x, y)?;
+            } else {
+                writeln!(writer, "    {} [label=\"\", shape=point, pos=\"{},{}!\"];", ids[&node], x, y)?;
+            }
+        }
+        for edge in graph.edge_references() {
+            writeln!(writer, "    {} -- {} [color={}];", ids[&edge.source()], ids[&edge.target()], edge.weight())?;
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+}
+
+// Numbers the nodes from 0 with no gaps
+fn node_ids(game: &Game) -> HashMap<NodeIndex, usize> {
+    game.get_graph().node_indices().enumerate().map(|(id, node)| (node, id)).collect()
+}
+
+fn build_game(turn: Color, grounds: Vec<u64>, nodes: Vec<(u64, (f32, f32))>, edges: Vec<(u64, u64, Color)>) -> Result<Game, LoadError> {
+    if grounds.is_empty() {
+        return Err(LoadError::MissingGround);
+    }
+    let mut graph = StableUnGraph::with_capacity(nodes.len(), edges.len());
+    let mut indexes = HashMap::new();
+    for (id, position) in nodes {
+        if indexes.insert(id, graph.add_node((false, position))).is_some() {
+            return Err(LoadError::DuplicateNode(id));
+        }
+    }
+    for (a, b, color) in edges {
+        let a = *indexes.get(&a).ok_or(LoadError::UnknownNode(a))?;
+        let b = *indexes.get(&b).ok_or(LoadError::UnknownNode(b))?;
+        graph.add_edge(a, b, color);
+    }
+    let grounds = grounds.into_iter()
+        .map(|id| indexes.get(&id).copied().ok_or(LoadError::UnknownNode(id)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut game = Game::with_grounds(graph, turn, grounds);
+    // Pieces that aren't attached to the ground would fall off straight away, so they're never in play
+    game.remove_floating();
+    Ok(game)
+}
+
+// JSON mirrors the text format, with the same renumbered node ids
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameFile {
+    turn: Color,
+    ground: Vec<u64>,
+    nodes: Vec<NodeEntry>,
+    edges: Vec<EdgeEntry>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct NodeEntry {
+    id: u64,
+    x: f32,
+    y: f32,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EdgeEntry {
+    a: u64,
+    b: u64,
+    color: Color,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Game {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let graph = self.get_graph();
+        let ids = node_ids(self);
+        let file = GameFile {
+            turn: self.get_turn(),
+            ground: self.grounds().iter().map(|node| ids[node] as u64).collect(),
+            nodes: graph.node_indices()
+                .map(|node| NodeEntry { id: ids[&node] as u64, x: graph[node].1.0, y: graph[node].1.1 })
+                .collect(),
+            edges: graph.edge_references()
+                .map(|edge| EdgeEntry { a: ids[&edge.source()] as u64, b: ids[&edge.target()] as u64, color: *edge.weight() })
+                .collect(),
+        };
+        file.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Game {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        let file = GameFile::deserialize(deserializer)?;
+        if file.turn == Color::Green {
+            return Err(serde::de::Error::custom("it can't be green's turn"));
+        }
+        build_game(
+            file.turn,
+            file.ground,
+            file.nodes.into_iter().map(|node| (node.id, (node.x, node.y))).collect(),
+            file.edges.into_iter().map(|edge| (edge.a, edge.b, edge.color)).collect(),
+        )
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Game {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(text: &str) -> serde_json::Result<Game> {
+        serde_json::from_str(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITION: &str = "# Two stalks on separate bits of ground
+turn red
+ground 4 7
+node 4 0 0
+node 7 3 0
+node 1 0 1
+node 2 0 2.5
+node 3 3 1
+edge 4 1 blue
+edge 1 2 green
+edge 7 3 red
+";
+
+    fn load(text: &str) -> Result<Game, LoadError> {
+        Game::from_reader(text.as_bytes())
+    }
+
+    fn save(game: &Game) -> String {
+        let mut text = vec![];
+        game.to_writer(&mut text).unwrap();
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        let game = load(POSITION).unwrap();
+        assert_eq!(game.get_turn(), Color::Red);
+        assert_eq!(game.grounds().len(), 2);
+        assert_eq!(game.get_graph().node_count(), 5);
+        assert_eq!(game.get_graph().edge_count(), 3);
+
+        let saved = save(&game);
+        let reloaded = load(&saved).unwrap();
+        assert_eq!(save(&reloaded), saved);
+        let positions = |game: &Game| game.get_graph().node_weights().map(|&(_, position)| position).collect::<Vec<_>>();
+        assert_eq!(positions(&reloaded), positions(&game));
+    }
+
+    #[test]
+    fn floating_pieces_are_dropped() {
+        let game = load(&format!("{}node 8 5 5\nnode 9 5 6\nedge 8 9 blue\n", POSITION)).unwrap();
+        assert_eq!(game.get_graph().node_count(), 5);
+        assert_eq!(game.get_graph().edge_count(), 3);
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        assert!(matches!(load(&format!("{}edge 1 5 red\n", POSITION)), Err(LoadError::UnknownNode(5))));
+        assert!(matches!(load(&format!("{}node 3 1 1\n", POSITION)), Err(LoadError::DuplicateNode(3))));
+        assert!(matches!(load(&POSITION.replace("ground 4 7\n", "")), Err(LoadError::MissingGround)));
+        assert!(matches!(load(&POSITION.replace("turn red\n", "")), Err(LoadError::MissingTurn)));
+        assert!(matches!(
+            load(&POSITION.replace("turn red", "turn green")),
+            Err(LoadError::InvalidLine { line: 2, .. })
+        ));
+        assert!(matches!(load("turn blue\nground 0\nnode 0 0 zero\n"), Err(LoadError::InvalidLine { line: 3, .. })));
+        for coordinate in ["NaN", "inf", "-infinity", "1e40"] {
+            let text = format!("turn blue\nground 0\nnode 0 {} 0\n", coordinate);
+            assert!(matches!(load(&text), Err(LoadError::InvalidLine { line: 3, .. })), "{} should be rejected", coordinate);
+        }
+    }
+
+    #[test]
+    fn dot_marks_colors_and_ground() {
+        let game = load(POSITION).unwrap();
+        let mut dot = vec![];
+        game.to_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("graph hackenbush {\n    label=\"red to move\";\n"));
+        assert!(dot.trim_end().ends_with('}'));
+        assert_eq!(dot.matches("shape=box").count(), 2);
+        assert_eq!(dot.matches("shape=point").count(), 3);
+        // Nodes are renumbered in the order they were placed, so the ground at 4 is 0
+        assert!(dot.contains("    0 [label=\"ground\", shape=box, pos=\"0,0!\"];"));
+        assert!(dot.contains("    0 -- 2 [color=blue];"));
+        assert!(dot.contains("    2 -- 3 [color=green];"));
+        assert!(dot.contains("    1 -- 4 [color=red];"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips() {
+        let game = load(POSITION).unwrap();
+        let json = game.to_json().unwrap();
+        let reloaded = Game::from_json(&json).unwrap();
+        assert_eq!(save(&reloaded), save(&game));
+        assert_eq!(reloaded.to_json().unwrap(), json);
+
+        assert!(Game::from_json(&json.replace(r#""turn": "red""#, r#""turn": "green""#)).is_err());
+        assert!(Game::from_json(&json.replace(r#""turn": "red""#, r#""turn": "purple""#)).is_err());
+        assert!(Game::from_json(&json.replace(r#""ground": ["#, r#""ground": [9, "#)).is_err());
+    }
+
+    #[test]
+    fn read_errors_are_passed_on() {
+        struct Broken;
+        impl io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk fell off"))
+            }
+        }
+        assert!(matches!(Game::from_reader(io::BufReader::new(Broken)), Err(LoadError::Io(_))));
+    }
+}
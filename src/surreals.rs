@@ -1,216 +1,1031 @@
 use std::cmp::Ordering;
-use std::collections::VecDeque;
-use std::iter::zip;
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::{Add, Bound, Div, Mul, Neg, RangeBounds, Sub};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use itertools::Itertools;
 use lazy_static::lazy_static;
+use num_bigint::BigInt;
+use crate::dyadic::Dyadic;
 
-const ZERO: RawSurreal = RawSurreal { left: None, right: None, actual_value: 0.0};
+// Floats that only approximate a value (like 0.1) use their whole mantissa, which needs a denominator
+// far past this. Anything finer would also be far too many days out to generate.
+const MAX_FLOAT_EXPONENT: u32 = 32;
+
+// Looking up a number generates every day up to its birthday, and day d alone has 2^d numbers, so past
+// this the table would take gigabytes. Tables that were loaded with more days can still use all of them.
+const MAX_BIRTHDAY: usize = 20;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SurrealError {
+    DivisionByZero,
+    /// Some left option was greater than or equal to some right option, so the form isn't a number
+    LeftNotLessThanRight,
+    /// The result isn't born on any finite day, like 1/3
+    NotDyadic,
+    /// The number is born too late for the table to reach, like 1000
+    BornTooLate,
+}
+
+impl fmt::Display for SurrealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurrealError::DivisionByZero => write!(f, "attempted to divide a surreal by zero"),
+            SurrealError::LeftNotLessThanRight => write!(f, "left options must all be less than right options"),
+            SurrealError::NotDyadic => write!(f, "result is not a dyadic rational"),
+            SurrealError::BornTooLate => write!(f, "number is born too late to generate"),
+        }
+    }
+}
+
+impl std::error::Error for SurrealError {}
+
+/// One step in a sign expansion, going right (`Plus`) or left (`Minus`) down the number tree
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RawSurreal {
-    left: Option<usize>,
-    right: Option<usize>,
-    actual_value: f64,
+    left: Vec<usize>,
+    right: Vec<usize>,
+    actual_value: Dyadic,
 }
 
 impl RawSurreal {
-    pub fn new(left: Option<usize>, right: Option<usize>, actual_value: f64) -> RawSurreal {
+    pub fn new(left: Vec<usize>, right: Vec<usize>, actual_value: Dyadic) -> RawSurreal {
         RawSurreal { left, right, actual_value}
     }
-    pub fn less_than(&self, other: &RawSurreal, list: &Vec<RawSurreal>) -> bool {
-        (self.left.is_none() || (self.left.is_some() && !other.less_than( &list[self.left.unwrap()], list )) )
-        && (other.right.is_none() || (other.right.is_some() && !list[other.right.unwrap()].less_than(self, list)) )
+    pub fn less_than(&self, other: &RawSurreal, list: &SurrealNumbers) -> bool {
+        // x <= y unless some XL >= y or some YR <= x
+        self.left.iter().all(|&l| !other.less_than(list.index(l), list))
+        && other.right.iter().all(|&r| !list.index(r).less_than(self, list))
     }
-    pub fn equal(&self, other: &RawSurreal, list: &Vec<RawSurreal>) -> bool {
+    /// Checks that every left option is strictly less than every right option
+    pub fn is_number(&self, list: &SurrealNumbers) -> bool {
+        self.left.iter().all(|&l| self.right.iter().all(|&r| !list.index(r).less_than(list.index(l), list)))
+    }
+    pub fn equal(&self, other: &RawSurreal, list: &SurrealNumbers) -> bool {
         self.less_than(other, list) && other.less_than(self, list)
     }
 }
 
+// Day d adds exactly 2^d numbers, so an index is enough to work out which day a number was born on.
+// Generating anywhere near this many days would run out of memory long before running out of slots.
+const MAX_DAYS: usize = 48;
+
+/// The table of every number born so far. It's append-only: once a day is generated it never changes,
+/// so reads don't need any locks, and the only lock is taken while a new day is being generated.
 pub struct SurrealNumbers {
-    numbers_line: VecDeque<usize>, // Vector of indexes
-    numbers_by_day: Vec<RawSurreal>,
-    day: usize,
+    days: [OnceLock<Box<[RawSurreal]>>; MAX_DAYS], // Numbers born on each day, indexed continuing from the day before
+    number_lines: [OnceLock<Box<[usize]>>; MAX_DAYS], // Indexes sorted by value, as of the end of each day
+    day: AtomicUsize,
+    generating: Mutex<()>,
+}
+
+impl Default for SurrealNumbers {
+    fn default() -> Self {
+        SurrealNumbers::new()
+    }
 }
 
 impl SurrealNumbers {
     pub fn new() -> SurrealNumbers {
-        SurrealNumbers { numbers_line: VecDeque::from([0]), numbers_by_day: vec![ZERO], day: 0 }
+        let numbers = SurrealNumbers {
+            days: std::array::from_fn(|_| OnceLock::new()),
+            number_lines: std::array::from_fn(|_| OnceLock::new()),
+            day: AtomicUsize::new(0),
+            generating: Mutex::new(()),
+        };
+        let zero = RawSurreal::new(vec![], vec![], Dyadic::zero());
+        numbers.days[0].set(Box::new([zero])).unwrap();
+        numbers.number_lines[0].set(Box::new([0])).unwrap();
+        numbers
+    }
+    pub fn generate_next_day(&self) {
+        let _generating = self.generating.lock().unwrap();
+        self.build_day(self.day() + 1);
     }
-    pub fn generate_next_day(&mut self) {
-        self.day += 1;
-        let base_length = self.numbers_by_day.len();
+    /// Generates days until `day` has been reached, if it hasn't been already
+    pub fn generate_until(&self, day: usize) {
+        if self.day() >= day {
+            return;
+        }
+        let _generating = self.generating.lock().unwrap();
+        // Someone else might have generated some days while we were waiting
+        while self.day() < day {
+            self.build_day(self.day() + 1);
+        }
+    }
+    // Must only be called while holding the generating lock
+    fn build_day(&self, day: usize) {
+        assert!(day < MAX_DAYS, "Can't generate past day {}", MAX_DAYS - 1);
+        let line = self.number_lines[day - 1].get().unwrap();
 
         // First, generate all new numbers
-        self.numbers_line.make_contiguous();
-        let mut new_numbers: Vec<_> = self.numbers_line.as_slices().0.windows(2)
-            .into_iter().map(|arr|
-                RawSurreal::new(Some(arr[0]), Some(arr[1]),
-            (self.numbers_by_day[arr[0]].actual_value + self.numbers_by_day[arr[1]].actual_value) / 2.0)
+        let mut new_numbers: Vec<_> = line.windows(2)
+            .map(|arr|
+                RawSurreal::new(vec![arr[0]], vec![arr[1]],
+            self.index(arr[0]).actual_value.midpoint(&self.index(arr[1]).actual_value))
             )
             .collect();
         // Add the ending numbers
-        new_numbers.push(RawSurreal::new(Some(*self.numbers_line.back().unwrap()), None, self.day as f64));
-        new_numbers.push(RawSurreal::new(None, Some(self.numbers_line[0]), (self.day as f64) * -1.0));
-        self.numbers_by_day.append(&mut new_numbers);
+        new_numbers.push(RawSurreal::new(vec![*line.last().unwrap()], vec![], Dyadic::from(day as i64)));
+        new_numbers.push(RawSurreal::new(vec![], vec![line[0]], -Dyadic::from(day as i64)));
+        self.install_day(day, new_numbers);
+    }
+    // Adds a day's numbers to the table. They have to be in the order `build_day` makes them: the
+    // midpoints from left to right, then the new largest number, then the new smallest.
+    fn install_day(&self, day: usize, new_numbers: Vec<RawSurreal>) {
+        let line = self.number_lines[day - 1].get().unwrap();
+        let base_length = (1 << day) - 1;
+        let new_length = new_numbers.len();
 
         // Update the number line
-        self.numbers_line = self.numbers_line.iter()
-            .zip(base_length..)
-            .map(|tup| [*tup.0, tup.1]) // There's gotta be a better way to do this
-            .flatten()
+        let new_line: Vec<_> = std::iter::once(base_length + new_length - 1)
+            .chain(line.iter()
+                .zip(base_length..)
+                .flat_map(|tup| [*tup.0, tup.1])) // There's gotta be a better way to do this
             .collect();
-        self.numbers_line.push_front(self.numbers_by_day.len() - 1);
+
+        self.days[day].set(new_numbers.into_boxed_slice()).expect("Day was already generated");
+        self.number_lines[day].set(new_line.into_boxed_slice()).expect("Day was already generated");
+        self.day.store(day, AtomicOrdering::Release);
+    }
+    // Splits an index into the day it was born on and its position within that day
+    fn locate(index: usize) -> (usize, usize) {
+        let day = (index + 1).ilog2() as usize;
+        (day, index + 1 - (1 << day))
     }
     fn index(&self, index: usize) -> &RawSurreal {
-        &self.numbers_by_day[index]
+        let (day, offset) = SurrealNumbers::locate(index);
+        &self.days[day].get().expect("Number hasn't been born yet")[offset]
+    }
+    pub fn day(&self) -> usize {
+        self.day.load(AtomicOrdering::Acquire)
+    }
+    pub fn birthday(&self, index: usize) -> usize {
+        SurrealNumbers::locate(index).0
     }
-    pub fn numbers(&self) -> &Vec<RawSurreal> {
-        &self.numbers_by_day
+    /// A number's parent in the tree is whichever of its options was born most recently. The sign says
+    /// which way you go from the parent to get here.
+    pub fn parent(&self, index: usize) -> Option<(usize, Sign)> {
+        let raw = self.index(index);
+        raw.left.iter().map(|&l| (l, Sign::Plus))
+            .chain(raw.right.iter().map(|&r| (r, Sign::Minus)))
+            .max_by_key(|&(option, _)| self.birthday(option))
     }
-    pub fn number_line(&self) -> Vec<&RawSurreal> {
-        self.numbers_line.iter().map(|idx| &self.numbers_by_day[*idx]).collect()
+    fn current_line(&self) -> &[usize] {
+        self.number_lines[self.day()].get().unwrap()
     }
-    pub fn number_line_reals(&self) -> Vec<f64> {
-        self.numbers_line.iter().map(|idx| self.numbers_by_day[*idx].actual_value).collect()
+    // The number line is sorted, so we can binary search it by value
+    fn find_value(&self, value: &Dyadic) -> Option<usize> {
+        let line = self.current_line();
+        line.binary_search_by(|&idx| self.index(idx).actual_value.cmp(value))
+            .ok()
+            .map(|position| line[position])
+    }
+    /// Finds the index of a value, generating days until it's been born
+    pub fn index_of_value(&self, value: &Dyadic) -> Result<usize, SurrealError> {
+        self.check_reachable(value)?;
+        self.generate_until(value.birthday());
+        Ok(self.find_value(value).expect("Number should have been born by now"))
+    }
+    /// Fails if looking up `value` would need more days than we're willing to generate
+    pub fn check_reachable(&self, value: &Dyadic) -> Result<(), SurrealError> {
+        if value.birthday() > MAX_BIRTHDAY.max(self.day()) {
+            return Err(SurrealError::BornTooLate);
+        }
+        Ok(())
+    }
+    /// Finds the index of { left | right }. By the simplicity rule, this is the earliest born number
+    /// strictly between the largest left option and the smallest right option, so we can work out its
+    /// value directly and binary search for it instead of comparing against every number we have.
+    pub fn find_simplest(&self, left: &[usize], right: &[usize]) -> Result<usize, SurrealError> {
+        let lower = left.iter().map(|&l| &self.index(l).actual_value).max();
+        let upper = right.iter().map(|&r| &self.index(r).actual_value).min();
+        self.simplest_within(lower, upper)
+    }
+    // Bounds of `None` are unbounded
+    fn simplest_within(&self, lower: Option<&Dyadic>, upper: Option<&Dyadic>) -> Result<usize, SurrealError> {
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            if lower >= upper {
+                return Err(SurrealError::LeftNotLessThanRight);
+            }
+        }
+        self.index_of_value(&Dyadic::simplest_between(lower, upper))
+    }
+    /// Every number generated so far, in the order they were born
+    pub fn numbers(&self) -> impl Iterator<Item = &RawSurreal> {
+        self.days[..=self.day()].iter().flat_map(|day| day.get().unwrap().iter())
+    }
+    pub fn number_line(&self) -> impl Iterator<Item = &RawSurreal> {
+        self.current_line().iter().map(|idx| self.index(*idx))
+    }
+    pub fn number_line_reals(&self) -> impl Iterator<Item = f64> + '_ {
+        self.current_line().iter().map(|idx| self.index(*idx).actual_value.to_f64())
+    }
+    pub fn number_line_values(&self) -> impl Iterator<Item = &Dyadic> {
+        self.current_line().iter().map(|idx| &self.index(*idx).actual_value)
+    }
+    pub fn value(&self, index: usize) -> &Dyadic {
+        &self.index(index).actual_value
+    }
+    /// Indexes of every number born by `day`, from smallest to largest, generating days if needed
+    pub fn line_until(&self, day: usize) -> impl Iterator<Item = usize> + '_ {
+        self.generate_until(day);
+        self.number_lines[day].get().unwrap().iter().copied()
+    }
+    /// Indexes of the numbers generated so far with values in `range`, from smallest to largest
+    pub fn in_range(&self, range: impl RangeBounds<Dyadic>) -> impl Iterator<Item = usize> + '_ {
+        let line = self.current_line();
+        let start = line.partition_point(|&idx| match range.start_bound() {
+            Bound::Included(lower) => self.value(idx) < lower,
+            Bound::Excluded(lower) => self.value(idx) <= lower,
+            Bound::Unbounded => false,
+        });
+        let end = line.partition_point(|&idx| match range.end_bound() {
+            Bound::Included(upper) => self.value(idx) <= upper,
+            Bound::Excluded(upper) => self.value(idx) < upper,
+            Bound::Unbounded => true,
+        });
+        line[start..end.max(start)].iter().copied()
+    }
+    /// The next smallest number generated so far
+    pub fn predecessor(&self, index: usize) -> Option<usize> {
+        let line = self.current_line();
+        let position = self.line_position(index);
+        position.checked_sub(1).map(|p| line[p])
+    }
+    /// The next largest number generated so far
+    pub fn successor(&self, index: usize) -> Option<usize> {
+        self.current_line().get(self.line_position(index) + 1).copied()
+    }
+    /// The index of the simplest number strictly between `lower` and `upper`, which is what
+    /// { lower | upper } equals, generating days until it's been born
+    pub fn simplest_between(&self, lower: &Dyadic, upper: &Dyadic) -> Result<usize, SurrealError> {
+        self.simplest_within(Some(lower), Some(upper))
+    }
+    fn line_position(&self, index: usize) -> usize {
+        let value = self.value(index);
+        self.current_line().binary_search_by(|&idx| self.value(idx).cmp(value))
+            .expect("Number should be on the number line")
+    }
+}
+
+/// Owns a table of surreal numbers. Every `Surreal` borrows the context it was made in, and can only be
+/// combined with other surreals from the same context.
+#[derive(Default)]
+pub struct SurrealContext {
+    numbers: SurrealNumbers,
+    // Arithmetic that's already been worked out, by the indexes of the operands. The recursive
+    // definitions keep asking for the same results, and without these they take exponential time.
+    sums: Cache<(usize, usize)>,
+    negations: Cache<usize>,
+    products: Cache<(usize, usize)>,
+}
+
+impl SurrealContext {
+    pub fn new() -> SurrealContext {
+        SurrealContext::with_table(SurrealNumbers::new())
+    }
+
+    /// Wraps a table that's already been generated, like one loaded from disk
+    pub fn with_table(numbers: SurrealNumbers) -> SurrealContext {
+        SurrealContext { numbers, sums: Cache::default(), negations: Cache::default(), products: Cache::default() }
+    }
+
+    pub fn table(&self) -> &SurrealNumbers {
+        &self.numbers
+    }
+
+    pub fn zero(&self) -> Surreal<'_> {
+        Surreal { index: 0, context: self }
+    }
+}
+
+impl fmt::Debug for SurrealContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SurrealContext").field("day", &self.numbers.day()).finish_non_exhaustive()
+    }
+}
+
+const CACHE_SHARDS: usize = 64;
+
+// Split into shards by key, each behind its own read-write
+// lock, so the threads of a parallel search only wait on each other when they add to the same shard.
+struct Cache<K> {
+    shards: [RwLock<HashMap<K, usize>>; CACHE_SHARDS],
+}
+
+impl<K> Default for Cache<K> {
+    fn default() -> Self {
+        Cache { shards: std::array::from_fn(|_| RwLock::default()) }
+    }
+}
+
+impl<K: Eq + Hash> Cache<K> {
+    fn shard(&self, key: &K) -> &RwLock<HashMap<K, usize>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % CACHE_SHARDS]
+    }
+
+    // Looks up a result, working it out if it isn't there yet. No lock is held while working it out,
+    // since that usually needs the same cache. Failures aren't cached.
+    fn get_or_insert_with(&self, key: K, compute: impl FnOnce() -> Result<usize, SurrealError>) -> Result<usize, SurrealError> {
+        let shard = self.shard(&key);
+        if let Some(&result) = shard.read().unwrap().get(&key) {
+            return Ok(result);
+        }
+        let result = compute()?;
+        shard.write().unwrap().insert(key, result);
+        Ok(result)
     }
 }
 
 lazy_static! {
-    pub static ref SURREALS: Mutex<SurrealNumbers> = Mutex::new( SurrealNumbers::new() );
-}
-
-#[derive(Copy, Clone, Eq, PartialEq, Debug)] // Should be alright to derive equality, since we won't have any duplicates
-pub struct Surreal {
-    index: usize
-}
-
-impl Surreal {
-    pub fn new(left: Option<Surreal>, right: Option<Surreal>) -> Surreal {
-        let raw = RawSurreal::new(left.map(|s| s.index), right.map(|s| s.index), 0.0);
-            // The actual value of this shouldn't matter
-        let mut unlocked_surreals = SURREALS.lock().unwrap();
-        // find equivalent
-        let mut found_idx = 0;
-        let mut found = false;
-        for (idx, num) in unlocked_surreals.numbers_by_day.iter().enumerate() {
-            found_idx = idx;
-            if raw.equal(num, &unlocked_surreals.numbers_by_day) {
-                found = true;
-                break;
+    /// Shared context for places like the GUI, where threading one through would just be noise
+    pub static ref SURREALS: SurrealContext = SurrealContext::new();
+}
+
+#[derive(Copy, Clone)]
+pub struct Surreal<'ctx> {
+    index: usize,
+    context: &'ctx SurrealContext,
+}
+
+impl<'ctx> Surreal<'ctx> {
+    pub fn new(context: &'ctx SurrealContext, left: &[Surreal<'ctx>], right: &[Surreal<'ctx>]) -> Result<Surreal<'ctx>, SurrealError> {
+        for option in left.iter().chain(right) {
+            assert!(std::ptr::eq(option.context, context), "Surreals come from different contexts");
+        }
+        let index = context.table().find_simplest(
+            &left.iter().map(|s| s.index).collect::<Vec<_>>(),
+            &right.iter().map(|s| s.index).collect::<Vec<_>>(),
+        )?;
+        Ok(Surreal { index, context })
+    }
+
+    pub fn zero(context: &'ctx SurrealContext) -> Surreal<'ctx> {
+        context.zero()
+    }
+
+    /// Gets the surreal for numerator / 2^exponent, generating days until it's been born
+    pub fn from_dyadic(context: &'ctx SurrealContext, numerator: impl Into<BigInt>, exponent: u32) -> Result<Surreal<'ctx>, SurrealError> {
+        Surreal::from_value(context, &Dyadic::new(numerator, exponent))
+    }
+
+    pub fn from_value(context: &'ctx SurrealContext, value: &Dyadic) -> Result<Surreal<'ctx>, SurrealError> {
+        let index = context.table().index_of_value(value)?;
+        Ok(Surreal { index, context })
+    }
+
+    pub fn from_f64(context: &'ctx SurrealContext, value: f64) -> Result<Surreal<'ctx>, SurrealError> {
+        let value = Dyadic::try_from(value)?;
+        if value.exponent() > MAX_FLOAT_EXPONENT {
+            return Err(SurrealError::NotDyadic);
+        }
+        Surreal::from_value(context, &value)
+    }
+
+    fn index(&self) -> usize { self.index }
+
+    /// An id that's unique to this number's value. Days are always generated in the same order, so a
+    /// value gets the same id in every table, including ones that were rebuilt or loaded from disk.
+    pub fn canonical_id(self) -> usize {
+        self.index
+    }
+
+    pub fn context(self) -> &'ctx SurrealContext {
+        self.context
+    }
+
+    // Builds a surreal from the same context as this one
+    fn sibling(self, index: usize) -> Surreal<'ctx> {
+        Surreal { index, context: self.context }
+    }
+
+    fn check_context(self, other: Surreal<'ctx>) {
+        assert!(std::ptr::eq(self.context, other.context), "Surreals come from different contexts");
+    }
+
+    /// Builds a number by following a sign expansion down from zero
+    pub fn from_sign_expansion(context: &'ctx SurrealContext, signs: &[Sign]) -> Surreal<'ctx> {
+        let mut lower = vec![];
+        let mut upper = vec![];
+        let mut current = context.zero();
+        for sign in signs {
+            match sign {
+                Sign::Plus => lower = vec![current],
+                Sign::Minus => upper = vec![current],
+            }
+            current = Surreal::new(context, &lower, &upper).expect("Sign expansion is too long to generate");
+        }
+        current
+    }
+
+    pub fn birthday(self) -> usize {
+        self.context.table().birthday(self.index())
+    }
+
+    /// The path of pluses and minuses from zero down to this number in the number tree
+    pub fn sign_expansion(self) -> Vec<Sign> {
+        let surreals = self.context.table();
+        let mut signs = vec![];
+        let mut index = self.index();
+        while let Some((parent, sign)) = surreals.parent(index) {
+            signs.push(sign);
+            index = parent;
+        }
+        signs.reverse();
+        signs
+    }
+
+    /// Returns the left and right options of this number's canonical form
+    pub fn options(self) -> (Vec<Surreal<'ctx>>, Vec<Surreal<'ctx>>) {
+        let surreals = self.context.table();
+        let raw = surreals.index(self.index());
+        (
+            raw.left.iter().map(|&index| self.sibling(index)).collect(),
+            raw.right.iter().map(|&index| self.sibling(index)).collect(),
+        )
+    }
+
+    /// Divides two surreals, as long as the quotient is born on some finite day.
+    ///
+    /// The quotient is found by walking down the number tree from zero, checking `candidate * other`
+    /// against `self` to pick a side each step. Non-dyadic quotients would never be reached this way, and
+    /// ones born too late would take too many days to reach, so both get rejected before the search starts.
+    pub fn checked_div(self, other: Surreal<'ctx>) -> Result<Surreal<'ctx>, SurrealError> {
+        self.check_context(other);
+        let zero = self.context.zero();
+        if other == zero {
+            return Err(SurrealError::DivisionByZero);
+        }
+        if other < zero {
+            return (-self).checked_div(-other);
+        }
+        let quotient = self.to_dyadic().checked_div(&other.to_dyadic()).ok_or(SurrealError::NotDyadic)?;
+        self.context.table().check_reachable(&quotient)?;
+
+        let mut lower = vec![];
+        let mut upper = vec![];
+        let mut candidate = zero;
+        loop {
+            match candidate.checked_mul(other)?.cmp(&self) {
+                Ordering::Equal => return Ok(candidate),
+                Ordering::Less => lower = vec![candidate],
+                Ordering::Greater => upper = vec![candidate],
             }
+            candidate = Surreal::new(self.context, &lower, &upper)?;
         }
-        let first_found_idx = found_idx + 1;
-        if !found {
-            // This should only be possible if we have a number from the most recent day
-            // In this case, make a new day, then check again
-            unlocked_surreals.generate_next_day();
-            for (idx, num) in unlocked_surreals.numbers_by_day[first_found_idx..].iter().enumerate() {
-                found_idx = idx + first_found_idx;
-                if raw.equal(num, &unlocked_surreals.numbers_by_day) {
-                    found = true;
-                    break;
+    }
+
+    /// Adds two surreals, failing if the sum (or some step on the way to it) is born too late
+    ///
+    /// x + y = { XL + y, x + YL | XR + y, x + YR }
+    pub fn checked_add(self, other: Surreal<'ctx>) -> Result<Surreal<'ctx>, SurrealError> {
+        self.check_context(other);
+        // Addition is commutative, so both orders share a cache entry
+        let key = (self.index.min(other.index), self.index.max(other.index));
+        let index = self.context.sums.get_or_insert_with(key, || {
+            self.context.table().check_reachable(&(self.to_dyadic() + other.to_dyadic()))?;
+            let (self_left, self_right) = self.options();
+            let (other_left, other_right) = other.options();
+
+            let left: Vec<_> = self_left.iter().map(|&l| l.checked_add(other))
+                .chain(other_left.iter().map(|&l| self.checked_add(l)))
+                .collect::<Result<_, _>>()?;
+            let right: Vec<_> = self_right.iter().map(|&r| r.checked_add(other))
+                .chain(other_right.iter().map(|&r| self.checked_add(r)))
+                .collect::<Result<_, _>>()?;
+
+            Ok(Surreal::new(self.context, &left, &right)?.index)
+        })?;
+        Ok(self.sibling(index))
+    }
+
+    pub fn checked_sub(self, other: Surreal<'ctx>) -> Result<Surreal<'ctx>, SurrealError> {
+        self.checked_add(-other)
+    }
+
+    /// Multiplies two surreals, failing if the product (or some step on the way to it) is born too late
+    ///
+    /// xy = { XL y + x YL - XL YL, XR y + x YR - XR YR | XL y + x YR - XL YR, XR y + x YL - XR YL }
+    pub fn checked_mul(self, other: Surreal<'ctx>) -> Result<Surreal<'ctx>, SurrealError> {
+        self.check_context(other);
+        let key = (self.index.min(other.index), self.index.max(other.index));
+        let index = self.context.products.get_or_insert_with(key, || {
+            self.context.table().check_reachable(&(self.to_dyadic() * other.to_dyadic()))?;
+            let (self_left, self_right) = self.options();
+            let (other_left, other_right) = other.options();
+            // Every intermediate result has to be born in the table, so this is worked out as a(y - b) + xb,
+            // which stays closer to zero than ay + xb does
+            let product_options = |a: &[Surreal<'ctx>], b: &[Surreal<'ctx>]| {
+                a.iter().cartesian_product(b.iter())
+                    .map(|(&a, &b)| a.checked_mul(other.checked_sub(b)?)?.checked_add(self.checked_mul(b)?))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+
+            let mut left = product_options(&self_left, &other_left)?;
+            left.append(&mut product_options(&self_right, &other_right)?);
+            let mut right = product_options(&self_left, &other_right)?;
+            right.append(&mut product_options(&self_right, &other_left)?);
+
+            Ok(Surreal::new(self.context, &left, &right)?.index)
+        })?;
+        Ok(self.sibling(index))
+    }
+
+    pub fn to_real(self) -> f64 {
+        self.context.table().index(self.index).actual_value.to_f64()
+    }
+
+    /// The exact value of this number
+    pub fn to_dyadic(self) -> Dyadic {
+        self.context.table().index(self.index).actual_value.clone()
+    }
+
+}
+
+impl fmt::Debug for Surreal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Surreal").field("index", &self.index).finish()
+    }
+}
+
+// Should be alright to compare indexes, since we won't have any duplicates in a table, and every table
+// puts each value at the same index
+impl PartialEq for Surreal<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical_id() == other.canonical_id()
+    }
+}
+
+impl Eq for Surreal<'_> {}
+
+impl Hash for Surreal<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_id().hash(state);
+    }
+}
+
+impl PartialOrd for Surreal<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Surreal<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Numbers from different tables can't see each other's options, but their values still compare
+        if !std::ptr::eq(self.context, other.context) {
+            return self.to_dyadic().cmp(&other.to_dyadic());
+        }
+        let surreals = self.context.table();
+        let self_raw = surreals.index(self.index);
+        let other_raw = surreals.index(other.index);
+
+        let le = self_raw.less_than(other_raw, surreals);
+        let ge = other_raw.less_than(self_raw, surreals);
+
+        if le & ge { Ordering::Equal }
+        else if le { Ordering::Less }
+        else { Ordering::Greater }
+    }
+}
+
+impl<'ctx> Add for Surreal<'ctx> {
+    type Output = Surreal<'ctx>;
+
+    fn add(self, other: Surreal<'ctx>) -> Surreal<'ctx> {
+        self.checked_add(other).expect("Sum is born too late to generate")
+    }
+}
+
+impl<'ctx> Neg for Surreal<'ctx> {
+    type Output = Surreal<'ctx>;
+
+    // -x = { -XR | -XL }
+    fn neg(self) -> Surreal<'ctx> {
+        // A negation is born on the same day, so it's always in the table already
+        let index = self.context.negations.get_or_insert_with(self.index, || {
+            let (left, right) = self.options();
+            let negated_right: Vec<_> = right.iter().map(|&r| -r).collect();
+            let negated_left: Vec<_> = left.iter().map(|&l| -l).collect();
+            Ok(Surreal::new(self.context, &negated_right, &negated_left)?.index)
+        });
+        self.sibling(index.expect("Negation of a number should be a number"))
+    }
+}
+
+impl<'ctx> Sub for Surreal<'ctx> {
+    type Output = Surreal<'ctx>;
+
+    fn sub(self, other: Surreal<'ctx>) -> Surreal<'ctx> {
+        self + -other
+    }
+}
+
+impl<'ctx> Mul for Surreal<'ctx> {
+    type Output = Surreal<'ctx>;
+
+    fn mul(self, other: Surreal<'ctx>) -> Surreal<'ctx> {
+        self.checked_mul(other).expect("Product is born too late to generate")
+    }
+}
+
+impl<'ctx> Div for Surreal<'ctx> {
+    type Output = Surreal<'ctx>;
+
+    fn div(self, other: Surreal<'ctx>) -> Surreal<'ctx> {
+        self.checked_div(other).expect("Quotient isn't a dyadic surreal")
+    }
+}
+
+// Conversions without an explicit context go through the shared one
+impl TryFrom<Dyadic> for Surreal<'static> {
+    type Error = SurrealError;
+
+    fn try_from(value: Dyadic) -> Result<Surreal<'static>, SurrealError> {
+        Surreal::from_value(&SURREALS, &value)
+    }
+}
+
+impl TryFrom<f64> for Surreal<'static> {
+    type Error = SurrealError;
+
+    fn try_from(value: f64) -> Result<Surreal<'static>, SurrealError> {
+        Surreal::from_f64(&SURREALS, value)
+    }
+}
+
+// The table is saved as the numbers born on each day. Loading checks every day has the right size and
+// every number has the value its options give it, rather than trusting whatever was on disk.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SurrealNumbers {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.days[..=self.day()].iter().map(|day| &**day.get().unwrap()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SurrealNumbers {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<SurrealNumbers, D::Error> {
+        use serde::de::Error;
+        let days = Vec::<Vec<RawSurreal>>::deserialize(deserializer)?;
+        if days.is_empty() || days.len() > MAX_DAYS {
+            return Err(D::Error::custom(format!("expected between 1 and {} days", MAX_DAYS)));
+        }
+        let numbers = SurrealNumbers::new();
+        for (day, new_numbers) in days.into_iter().enumerate() {
+            if new_numbers.len() != 1 << day {
+                return Err(D::Error::custom(format!("day {} should have {} numbers", day, 1usize << day)));
+            }
+            let born_before = (1 << day) - 1;
+            for raw in &new_numbers {
+                if raw.left.iter().chain(&raw.right).any(|&option| option >= born_before) {
+                    return Err(D::Error::custom(format!("a number born on day {} has an option born later", day)));
+                }
+                let lower = raw.left.iter().map(|&l| &numbers.index(l).actual_value).max();
+                let upper = raw.right.iter().map(|&r| &numbers.index(r).actual_value).min();
+                if lower.zip(upper).is_some_and(|(l, u)| l >= u)
+                    || raw.actual_value != Dyadic::simplest_between(lower, upper) {
+                    return Err(D::Error::custom(format!("{} doesn't match its options", raw.actual_value)));
                 }
             }
-            if !found {
-                panic!("Wasn't able to find surreal")
+            if day > 0 {
+                numbers.install_day(day, new_numbers);
             }
         }
+        let line = numbers.current_line();
+        if !line.windows(2).all(|pair| numbers.index(pair[0]).actual_value < numbers.index(pair[1]).actual_value) {
+            return Err(D::Error::custom("numbers are out of order"));
+        }
+        Ok(numbers)
+    }
+}
+
+// Surreals are saved as their values, so they can be loaded into any table
+#[cfg(feature = "serde")]
+impl serde::Serialize for Surreal<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_dyadic().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Surreal<'static> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Surreal<'static>, D::Error> {
+        serde::de::DeserializeSeed::deserialize(SurrealSeed(&SURREALS), deserializer)
+    }
+}
+
+/// Deserializes a surreal into a particular context. `Surreal` can be deserialized directly too, but only
+/// into the shared one.
+#[cfg(feature = "serde")]
+pub struct SurrealSeed<'ctx>(pub &'ctx SurrealContext);
+
+#[cfg(feature = "serde")]
+impl<'de, 'ctx> serde::de::DeserializeSeed<'de> for SurrealSeed<'ctx> {
+    type Value = Surreal<'ctx>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Surreal<'ctx>, D::Error> {
+        use serde::de::Error;
+        let value = <Dyadic as serde::Deserialize>::deserialize(deserializer)?;
+        Surreal::from_value(self.0, &value).map_err(|error| D::Error::custom(format!("couldn't load {}: {}", value, error)))
+    }
+}
 
-        Surreal { index: found_idx }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const DAYS: usize = 8;
+    const SAMPLES: usize = 2000;
+
+    // A random number born on or before `DAYS`, built from a random sign expansion
+    fn random_surreal<'ctx>(context: &'ctx SurrealContext, rng: &mut StdRng) -> Surreal<'ctx> {
+        let length = rng.gen_range(0..=DAYS);
+        let signs: Vec<_> = (0..length)
+            .map(|_| if rng.gen() { Sign::Plus } else { Sign::Minus })
+            .collect();
+        Surreal::from_sign_expansion(context, &signs)
+    }
+
+    fn less_or_equal(a: Surreal, b: Surreal) -> bool {
+        let table = a.context().table();
+        table.index(a.index()).less_than(table.index(b.index()), table)
     }
 
-    pub unsafe fn new_with_number_collection(left: Option<Surreal>, right: Option<Surreal>, number_collection: &mut SurrealNumbers) -> Surreal {
-        let raw = RawSurreal::new(left.map(|s| s.index), right.map(|s| s.index), 0.0);
-        // The actual value of this shouldn't matter
-        // find equivalent
-        let mut found_idx = 0;
-        let mut found = false;
-        for (idx, num) in number_collection.numbers_by_day.iter().enumerate() {
-            found_idx = idx;
-            if raw.equal(num, &number_collection.numbers_by_day) {
-                found = true;
-                break;
+    #[test]
+    fn ordering_is_transitive() {
+        let context = SurrealContext::new();
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..SAMPLES {
+            let (a, b, c) = (random_surreal(&context, &mut rng), random_surreal(&context, &mut rng), random_surreal(&context, &mut rng));
+            if less_or_equal(a, b) && less_or_equal(b, c) {
+                assert!(less_or_equal(a, c), "{} <= {} <= {} but not {} <= {}", a, b, c, a, c);
             }
         }
-        if !found {
-            // This should only be possible if we have a number from the most recent day
-            // In this case, make a new day, then check again
-            number_collection.generate_next_day();
-            for (idx, num) in number_collection.numbers_by_day[found_idx + 1..].iter().enumerate() {
-                found_idx = idx;
-                if raw.equal(num, &number_collection.numbers_by_day) {
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                panic!("Wasn't able to find surreal")
+    }
+
+    #[test]
+    fn ordering_is_antisymmetric() {
+        let context = SurrealContext::new();
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..SAMPLES {
+            let (a, b) = (random_surreal(&context, &mut rng), random_surreal(&context, &mut rng));
+            if less_or_equal(a, b) && less_or_equal(b, a) {
+                assert_eq!(a.index(), b.index());
             }
+            // Every pair of numbers is comparable one way or the other
+            assert!(less_or_equal(a, b) || less_or_equal(b, a));
         }
-        let index = found_idx as usize;
-        Surreal { index }
     }
 
-    fn index(&self) -> usize { self.index }
+    #[test]
+    fn values_agree_with_ordering() {
+        let context = SurrealContext::new();
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..SAMPLES {
+            let (a, b) = (random_surreal(&context, &mut rng), random_surreal(&context, &mut rng));
+            assert_eq!(less_or_equal(a, b), a.to_dyadic() <= b.to_dyadic(), "comparing {} and {}", a, b);
+            assert_eq!(a.cmp(&b), a.to_dyadic().cmp(&b.to_dyadic()));
+        }
+    }
 
-    pub fn to_real(&self) -> f64 {
-        // This is pretty slow, but should be fine
-        /*
-        let surreals = SURREALS.lock().unwrap();
-        let raw_self = &surreals.numbers_by_day[self.index];
-        let mut lower_bound = 0;
-        let mut upper_bound = surreals.numbers_line.len();
-
-        let mut lower_bound_number = -1.0 * (surreals.numbers_line.len() / 2) as f64;
-        let mut upper_bound_number = -1.0 * lower_bound_number;
-
-        while lower_bound != upper_bound {
-            println!("Finding real value, \
-                lower_bound_number: {lower_bound_number}, upper_bound_number: {upper_bound_number},\
-                lower_bound: {lower_bound}, upper_bound: {upper_bound}");
-            let guess_idx = (upper_bound - lower_bound) / 2;
-            let guess_number = &surreals.numbers_by_day[ surreals.numbers_line[guess_idx] ];
-            if raw_self.equal(guess_number, &surreals.numbers_by_day) {
-                return (upper_bound_number + lower_bound_number) / 2.0;
+    #[test]
+    fn number_line_is_sorted() {
+        let context = SurrealContext::new();
+        let table = context.table();
+        table.generate_until(DAYS);
+        for day in 0..=DAYS {
+            let line: Vec<_> = table.line_until(day).collect();
+            assert_eq!(line.len(), (1 << (day + 1)) - 1);
+            for pair in line.windows(2) {
+                let (a, b) = (table.index(pair[0]), table.index(pair[1]));
+                assert!(a.less_than(b, table) && !b.less_than(a, table));
+                assert!(a.actual_value < b.actual_value);
             }
-            else if raw_self.less_than(guess_number, &surreals.numbers_by_day) {
-                upper_bound = guess_idx;
-                upper_bound_number = (lower_bound_number + upper_bound_number) / 2.0
+        }
+    }
+
+    #[test]
+    fn parents_form_a_binary_tree() {
+        let context = SurrealContext::new();
+        let table = context.table();
+        table.generate_until(DAYS);
+        let mut children = std::collections::HashSet::new();
+        for index in 1..(1 << (DAYS + 1)) - 1 {
+            let (parent, sign) = table.parent(index).expect("Only zero has no parent");
+            assert_eq!(table.birthday(parent) + 1, table.birthday(index));
+            // Each number has at most one child on each side, on the matching side of it
+            assert!(children.insert((parent, sign)), "{} has two children going {:?}", parent, sign);
+            let (parent_value, value) = (table.value(parent), table.value(index));
+            match sign {
+                Sign::Plus => assert!(parent_value < value),
+                Sign::Minus => assert!(parent_value > value),
             }
-            else {
-                lower_bound = guess_idx;
-                lower_bound_number = (lower_bound_number + upper_bound_number) / 2.0
+        }
+        assert_eq!(table.parent(0), None);
+    }
+
+    // Numbers from the newest day used to come out wrong when built from their options, since one lookup
+    // forgot to offset into the table. Every way of making a number should now find the same one.
+    #[test]
+    fn newest_day_lookups_agree() {
+        let context = SurrealContext::new();
+        let table = context.table();
+        for day in 1..=DAYS {
+            table.generate_next_day();
+            let newest = (1 << day) - 1..(1 << (day + 1)) - 1;
+            for index in newest {
+                let value = table.value(index).clone();
+                let raw = table.index(index);
+                let left: Vec<_> = raw.left.iter().map(|&l| Surreal { index: l, context: &context }).collect();
+                let right: Vec<_> = raw.right.iter().map(|&r| Surreal { index: r, context: &context }).collect();
+
+                let from_options = Surreal::new(&context, &left, &right).unwrap();
+                let from_value = Surreal::from_value(&context, &value).unwrap();
+                assert_eq!(from_options.index(), index, "building {} from its options", value);
+                assert_eq!(from_value.index(), index, "looking up {}", value);
+                assert_eq!(from_options.to_dyadic(), value);
+                if let ([l], [r]) = (&raw.left[..], &raw.right[..]) {
+                    assert_eq!(table.simplest_between(table.value(*l), table.value(*r)), Ok(index));
+                }
+
+                // The shared context has to agree too, whatever it's generated so far
+                let shared = Surreal::try_from(value.clone()).unwrap();
+                assert_eq!(shared, from_value);
+                assert_eq!(shared.to_dyadic(), value);
             }
         }
+    }
 
-        panic!("Didn't find match")
-         */
-        let surreals = SURREALS.lock().unwrap();
-        surreals.numbers_by_day[self.index].actual_value
+    #[test]
+    fn ranges_follow_the_number_line() {
+        let context = SurrealContext::new();
+        let table = context.table();
+        table.generate_until(3);
+        fn values(table: &SurrealNumbers, indexes: impl Iterator<Item = usize>) -> Vec<String> {
+            indexes.map(|i| table.value(i).to_string()).collect()
+        }
+        let (one, half, two) = (Dyadic::from(1), Dyadic::new(1, 1), Dyadic::from(2));
+
+        assert_eq!(values(table, table.in_range(-one.clone()..half.clone())), ["-1", "-3/4", "-1/2", "-1/4", "0", "1/4"]);
+        assert_eq!(values(table, table.in_range(-one.clone()..=half.clone())).last().unwrap(), "1/2");
+        assert_eq!(values(table, table.in_range((Bound::Excluded(two.clone()), Bound::Unbounded))), ["3"]);
+        assert_eq!(values(table, table.in_range(..-two.clone())), ["-3"]);
+        assert_eq!(values(table, table.in_range(..=-two.clone())), ["-3", "-2"]);
+        assert_eq!(table.in_range(one.clone()..one.clone()).count(), 0);
+        assert_eq!(table.in_range(one.clone()..-one.clone()).count(), 0);
+        assert_eq!(table.in_range(..).count(), 15);
+
+        let index = |value: &Dyadic| table.index_of_value(value).unwrap();
+        assert_eq!(table.predecessor(0).map(|i| table.value(i).to_string()), Some("-1/4".to_string()));
+        assert_eq!(table.successor(0).map(|i| table.value(i).to_string()), Some("1/4".to_string()));
+        assert_eq!(table.successor(index(&Dyadic::from(2))), Some(index(&Dyadic::from(3))));
+        assert_eq!(table.successor(index(&Dyadic::from(3))), None);
+        assert_eq!(table.predecessor(index(&Dyadic::from(-3))), None);
     }
 
-}
+    #[test]
+    fn simplest_between_excludes_its_bounds() {
+        let context = SurrealContext::new();
+        let table = context.table();
+        let between = |lower: Dyadic, upper: Dyadic| table.simplest_between(&lower, &upper).map(|i| table.value(i).clone());
+        assert_eq!(between(Dyadic::from(-1), Dyadic::from(1)), Ok(Dyadic::zero()));
+        assert_eq!(between(Dyadic::zero(), Dyadic::from(1)), Ok(Dyadic::new(1, 1)));
+        assert_eq!(between(Dyadic::from(1), Dyadic::new(3, 1)), Ok(Dyadic::new(5, 2)));
+        assert_eq!(between(Dyadic::new(1, 2), Dyadic::from(3)), Ok(Dyadic::from(1)));
+        // Past the ends of everything generated so far
+        assert_eq!(between(Dyadic::from(3), Dyadic::from(1000)), Ok(Dyadic::from(4)));
+        assert_eq!(between(Dyadic::from(-1000), Dyadic::from(-5)), Ok(Dyadic::from(-6)));
+        assert_eq!(between(Dyadic::from(1), Dyadic::from(1)), Err(SurrealError::LeftNotLessThanRight));
+        assert_eq!(between(Dyadic::from(1), Dyadic::zero()), Err(SurrealError::LeftNotLessThanRight));
+        assert_eq!(between(Dyadic::from(30), Dyadic::from(40)), Err(SurrealError::BornTooLate));
+    }
 
-impl PartialOrd for Surreal {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let surreals = SURREALS.lock().unwrap();
-        let self_raw = &surreals.numbers_by_day[self.index];
-        let other_raw = &surreals.numbers_by_day[other.index];
+    #[test]
+    fn sign_expansions_round_trip() {
+        let context = SurrealContext::new();
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..SAMPLES {
+            let number = random_surreal(&context, &mut rng);
+            let signs = number.sign_expansion();
+            assert_eq!(signs.len(), number.birthday());
+            assert_eq!(Surreal::from_sign_expansion(&context, &signs), number);
+        }
+    }
 
-        let mut le = self_raw.less_than(other_raw, &surreals.numbers_by_day);
-        let mut ge = other_raw.less_than(self_raw, &surreals.numbers_by_day);
+    // Sums where one side is born on day 10 or later, checked against their exact values
+    #[test]
+    fn late_numbers_add_up() {
+        let context = SurrealContext::new();
+        let pairs = [
+            (Dyadic::from(10), Dyadic::from(-9)),
+            (Dyadic::from(12), Dyadic::from(-12)),
+            (Dyadic::new(1, 9), Dyadic::new(-3, 10)),
+            (Dyadic::new(21, 4), Dyadic::new(-1023, 10)),
+        ];
+        for (a, b) in pairs {
+            let (x, y) = (Surreal::from_value(&context, &a).unwrap(), Surreal::from_value(&context, &b).unwrap());
+            assert!(x.birthday() >= 10 || y.birthday() >= 10);
+            assert_eq!((x + y).to_dyadic(), &a + &b, "adding {} and {}", a, b);
+            assert_eq!(x + y - y, x);
+        }
+    }
 
-        Some (
-            if le & ge { Ordering::Equal }
-            else if le { Ordering::Less }
-            else { Ordering::Greater }
-        )
+    #[test]
+    fn products_and_quotients_agree_with_values() {
+        let context = SurrealContext::new();
+        let values = [Dyadic::from(4), Dyadic::from(-3), Dyadic::new(3, 2), Dyadic::new(-5, 3), Dyadic::new(1, 1)];
+        for a in &values {
+            for b in &values {
+                let (x, y) = (Surreal::from_value(&context, a).unwrap(), Surreal::from_value(&context, b).unwrap());
+                let product = x * y;
+                assert_eq!(product.to_dyadic(), a * b, "multiplying {} and {}", a, b);
+                assert_eq!(product.checked_div(y), Ok(x), "dividing {} by {}", product, b);
+            }
+        }
+        let (one, tiny) = (Surreal::from_dyadic(&context, 1, 0).unwrap(), Surreal::from_dyadic(&context, 1, 10).unwrap());
+        assert_eq!(one.checked_div(tiny), Err(SurrealError::BornTooLate));
+        assert_eq!((-one).checked_div(tiny), Err(SurrealError::BornTooLate));
+        assert_eq!(tiny.checked_div(one), Ok(tiny));
     }
-}
 
+    // Looking these up would try to generate more days than fit in memory
+    #[test]
+    fn late_values_are_rejected() {
+        let context = SurrealContext::new();
+        assert_eq!(Surreal::from_f64(&context, 100.0), Err(SurrealError::BornTooLate));
+        assert_eq!(Surreal::from_dyadic(&context, 1, 40), Err(SurrealError::BornTooLate));
+        assert_eq!(Surreal::from_dyadic(&context, -30, 0), Err(SurrealError::BornTooLate));
+        assert!(Surreal::from_dyadic(&context, 5, 4).is_ok());
+        assert_eq!(context.table().day(), 5);
+    }
 
-impl Ord for Surreal {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tables_round_trip() {
+        use serde::de::DeserializeSeed;
+        let context = SurrealContext::new();
+        context.table().generate_until(5);
+        let saved = serde_json::to_string(context.table()).unwrap();
+        let loaded = SurrealContext::with_table(serde_json::from_str(&saved).unwrap());
+        assert_eq!(loaded.table().day(), 5);
+        assert!(loaded.table().number_line_values().eq(context.table().number_line_values()));
+
+        // Surreals are saved as their values, so they load into any context
+        let number = Surreal::from_dyadic(&context, -11, 3).unwrap();
+        let text = serde_json::to_string(&number).unwrap();
+        assert_eq!(text, r#""-11/8""#);
+        let reloaded = SurrealSeed(&loaded).deserialize(&mut serde_json::Deserializer::from_str(&text)).unwrap();
+        assert!(std::ptr::eq(reloaded.context(), &loaded));
+        assert_eq!(reloaded.to_dyadic(), number.to_dyadic());
+        assert!(serde_json::from_str::<Surreal>(r#""1000""#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tampered_tables_are_rejected() {
+        let zero = r#"[{"left":[],"right":[],"actual_value":"0"}]"#;
+        let one = r#"{"left":[0],"right":[],"actual_value":"1"}"#;
+        let minus_one = r#"{"left":[],"right":[0],"actual_value":"-1"}"#;
+        let load = |day_one: &str| serde_json::from_str::<SurrealNumbers>(&format!("[{},[{}]]", zero, day_one));
+
+        assert!(load(&format!("{},{}", one, minus_one)).is_ok());
+        let error = |day_one: &str| load(day_one).err().expect("Table should be rejected").to_string();
+        assert!(error(one).contains("day 1 should have 2 numbers"));
+        assert!(error(&format!("{},{}", one.replace("[0]", "[1]"), minus_one)).contains("option born later"));
+        assert!(error(&format!("{},{}", one.replace(r#""1""#, r#""2""#), minus_one)).contains("doesn't match its options"));
+        assert!(error(&format!("{},{}", minus_one, one)).contains("out of order"));
+        assert!(serde_json::from_str::<SurrealNumbers>("[]").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn late_results_are_rejected() {
+        let context = SurrealContext::new();
+        let (sixteen, twelve, four) = (
+            Surreal::from_dyadic(&context, 16, 0).unwrap(),
+            Surreal::from_dyadic(&context, 12, 0).unwrap(),
+            Surreal::from_dyadic(&context, 4, 0).unwrap(),
+        );
+        assert_eq!(sixteen.checked_add(sixteen), Err(SurrealError::BornTooLate));
+        assert_eq!(sixteen.checked_sub(-sixteen), Err(SurrealError::BornTooLate));
+        assert_eq!(sixteen.checked_mul(four), Err(SurrealError::BornTooLate));
+        assert_eq!(twelve.checked_add(four), Ok(sixteen));
+        assert_eq!(four.checked_mul(four), Ok(sixteen));
+        // Nothing past the largest operand had to be generated to find that out
+        assert_eq!(context.table().day(), 16);
+    }
+}
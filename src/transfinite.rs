@@ -0,0 +1,297 @@
+//! Surreals that aren't born on any finite day, like ω, ε = 1/ω, ω - 1 and ω/2. These can't live in the
+//! number table, so they're kept symbolically in Conway normal form: a finite sum of dyadic multiples of
+//! powers of ω, where negative powers are infinitesimals (ω^-1 = ε).
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use crate::dyadic::Dyadic;
+use crate::surreals::{Surreal, SurrealContext};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(from = "SavedTerms"))]
+pub struct Transfinite {
+    terms: Vec<(i32, Dyadic)>, // (power of ω, coefficient), largest power first, with no zero coefficients
+}
+
+impl Transfinite {
+    pub fn new(terms: impl IntoIterator<Item = (i32, Dyadic)>) -> Transfinite {
+        let zero = Dyadic::zero();
+        let mut terms: Vec<(i32, Dyadic)> = terms.into_iter().collect();
+        terms.sort_by(|(a, _), (b, _)| b.cmp(a));
+        // Combine terms with the same power
+        let mut combined: Vec<(i32, Dyadic)> = vec![];
+        for (power, coefficient) in terms {
+            match combined.last_mut() {
+                Some((last, sum)) if *last == power => *sum = &*sum + &coefficient,
+                _ => combined.push((power, coefficient)),
+            }
+        }
+        combined.retain(|(_, coefficient)| *coefficient != zero);
+        Transfinite { terms: combined }
+    }
+
+    pub fn zero() -> Transfinite {
+        Transfinite { terms: vec![] }
+    }
+
+    /// ω^power, so ω itself is `omega_power(1)` and ε is `omega_power(-1)`
+    pub fn omega_power(power: i32) -> Transfinite {
+        Transfinite::new([(power, Dyadic::from(1))])
+    }
+
+    pub fn omega() -> Transfinite {
+        Transfinite::omega_power(1)
+    }
+
+    pub fn epsilon() -> Transfinite {
+        Transfinite::omega_power(-1)
+    }
+
+    pub fn from_dyadic(value: Dyadic) -> Transfinite {
+        Transfinite::new([(0, value)])
+    }
+
+    /// The terms in normal form, from the largest power of ω down
+    pub fn terms(&self) -> &[(i32, Dyadic)] {
+        &self.terms
+    }
+
+    /// The coefficient on ω^power, which is zero if there's no such term
+    pub fn coefficient(&self, power: i32) -> Dyadic {
+        self.terms.iter()
+            .find(|(p, _)| *p == power)
+            .map(|(_, coefficient)| coefficient.clone())
+            .unwrap_or_else(Dyadic::zero)
+    }
+
+    /// Whether this is an ordinary dyadic, with no infinite or infinitesimal part
+    pub fn is_finite(&self) -> bool {
+        self.terms.iter().all(|(power, _)| *power == 0)
+    }
+
+    /// Whether this is larger in size than every real number, like ω or -ω/2
+    pub fn is_infinite(&self) -> bool {
+        self.terms.first().is_some_and(|(power, _)| *power > 0)
+    }
+
+    /// Whether this is smaller in size than every positive real number, but not zero, like ε
+    pub fn is_infinitesimal(&self) -> bool {
+        self.terms.first().is_some_and(|(power, _)| *power < 0)
+    }
+
+    pub fn to_dyadic(&self) -> Option<Dyadic> {
+        self.is_finite().then(|| self.coefficient(0))
+    }
+
+    /// The finite surreal with this value, if it is finite and born early enough to look up
+    pub fn to_surreal<'ctx>(&self, context: &'ctx SurrealContext) -> Option<Surreal<'ctx>> {
+        self.to_dyadic().and_then(|value| Surreal::from_value(context, &value).ok())
+    }
+}
+
+// Loaded terms go through `new`, since nothing says they were saved in normal form
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SavedTerms {
+    terms: Vec<(i32, Dyadic)>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedTerms> for Transfinite {
+    fn from(saved: SavedTerms) -> Transfinite {
+        Transfinite::new(saved.terms)
+    }
+}
+
+impl From<Dyadic> for Transfinite {
+    fn from(value: Dyadic) -> Transfinite {
+        Transfinite::from_dyadic(value)
+    }
+}
+
+impl From<Surreal<'_>> for Transfinite {
+    fn from(number: Surreal<'_>) -> Transfinite {
+        Transfinite::from_dyadic(number.to_dyadic())
+    }
+}
+
+impl PartialOrd for Transfinite {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// The largest power of ω where the two differ decides everything, since any multiple of a smaller power
+// is infinitely smaller
+impl Ord for Transfinite {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let difference = self.clone() - other.clone();
+        match difference.terms.first() {
+            Some((_, coefficient)) => coefficient.cmp(&Dyadic::zero()),
+            None => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialEq<Surreal<'_>> for Transfinite {
+    fn eq(&self, other: &Surreal<'_>) -> bool {
+        self.to_dyadic().is_some_and(|value| value == other.to_dyadic())
+    }
+}
+
+impl PartialOrd<Surreal<'_>> for Transfinite {
+    fn partial_cmp(&self, other: &Surreal<'_>) -> Option<Ordering> {
+        Some(self.cmp(&Transfinite::from(*other)))
+    }
+}
+
+impl Add for Transfinite {
+    type Output = Transfinite;
+
+    fn add(self, other: Transfinite) -> Transfinite {
+        Transfinite::new(self.terms.into_iter().chain(other.terms))
+    }
+}
+
+impl Add<Surreal<'_>> for Transfinite {
+    type Output = Transfinite;
+
+    fn add(self, other: Surreal<'_>) -> Transfinite {
+        self + Transfinite::from(other)
+    }
+}
+
+impl Neg for Transfinite {
+    type Output = Transfinite;
+
+    fn neg(self) -> Transfinite {
+        Transfinite::new(self.terms.into_iter().map(|(power, coefficient)| (power, -coefficient)))
+    }
+}
+
+impl Sub for Transfinite {
+    type Output = Transfinite;
+
+    fn sub(self, other: Transfinite) -> Transfinite {
+        self + -other
+    }
+}
+
+impl Sub<Surreal<'_>> for Transfinite {
+    type Output = Transfinite;
+
+    fn sub(self, other: Surreal<'_>) -> Transfinite {
+        self - Transfinite::from(other)
+    }
+}
+
+impl Mul for Transfinite {
+    type Output = Transfinite;
+
+    // Multiplies out term by term, using ω^a * ω^b = ω^(a + b)
+    fn mul(self, other: Transfinite) -> Transfinite {
+        Transfinite::new(self.terms.iter().flat_map(|(a, x)| {
+            other.terms.iter().map(move |(b, y)| (a + b, x * y))
+        }))
+    }
+}
+
+impl Mul<Dyadic> for Transfinite {
+    type Output = Transfinite;
+
+    fn mul(self, other: Dyadic) -> Transfinite {
+        self * Transfinite::from_dyadic(other)
+    }
+}
+
+// Writes things like ω - 1, (1/2)ω, ω^2 + 3ε or 5. Fractions go in brackets, since 1/2ω reads as 1/(2ω).
+impl fmt::Display for Transfinite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let zero = Dyadic::zero();
+        let one = Dyadic::from(1);
+        for (i, (power, coefficient)) in self.terms.iter().enumerate() {
+            let magnitude = if *coefficient < zero { -coefficient.clone() } else { coefficient.clone() };
+            match (i, *coefficient < zero) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            if *power == 0 {
+                write!(f, "{}", magnitude)?;
+            } else if !magnitude.is_integer() {
+                write!(f, "({})", magnitude)?;
+            } else if magnitude != one {
+                write!(f, "{}", magnitude)?;
+            }
+            match *power {
+                0 => {}
+                1 => write!(f, "ω")?,
+                -1 => write!(f, "ε")?,
+                power if power > 0 => write!(f, "ω^{}", power)?,
+                power => write!(f, "ε^{}", -power)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finite(numerator: i64, exponent: u32) -> Transfinite {
+        Transfinite::from_dyadic(Dyadic::new(numerator, exponent))
+    }
+
+    #[test]
+    fn infinitesimals_are_below_every_positive_number() {
+        let epsilon = Transfinite::epsilon();
+        assert!(epsilon > Transfinite::zero());
+        for k in 0..40 {
+            assert!(epsilon < finite(1, k), "ε should be less than 1/2^{}", k);
+            assert!(-epsilon.clone() > finite(-1, k));
+        }
+        assert!(epsilon.clone() * Dyadic::from(1000) < finite(1, 30));
+        assert!(Transfinite::omega_power(-2) < epsilon);
+    }
+
+    #[test]
+    fn infinities_are_above_every_real_number() {
+        let omega = Transfinite::omega();
+        assert!(omega.clone() - Transfinite::from(Dyadic::from(1)) < omega);
+        assert!(omega.clone() + Transfinite::epsilon() > omega);
+        for n in [0, 1, 1000, i64::MAX] {
+            assert!(omega > finite(n, 0));
+            assert!(omega.clone() * Dyadic::new(1, 20) > finite(n, 0));
+            assert!(-omega.clone() < finite(-n, 0));
+        }
+        assert!(Transfinite::omega_power(2) > omega.clone() * Dyadic::from(1000));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn loading_puts_terms_in_normal_form() {
+        let loaded: Transfinite = serde_json::from_str(r#"{"terms":[[0,"1"],[1,"0"],[0,"1"]]}"#).unwrap();
+        assert_eq!(loaded, finite(2, 0));
+        let loaded: Transfinite = serde_json::from_str(r#"{"terms":[[-1,"3"],[1,"1/2"]]}"#).unwrap();
+        assert_eq!(loaded.terms(), &[(1, Dyadic::new(1, 1)), (-1, Dyadic::from(3))]);
+        let saved = serde_json::to_string(&loaded).unwrap();
+        assert_eq!(serde_json::from_str::<Transfinite>(&saved).unwrap(), loaded);
+    }
+
+    #[test]
+    fn terms_print_in_order() {
+        let omega = Transfinite::omega();
+        assert_eq!((omega.clone() - finite(1, 0)).to_string(), "ω - 1");
+        assert_eq!((omega.clone() * Dyadic::new(1, 1)).to_string(), "(1/2)ω");
+        assert_eq!((-omega.clone() * Dyadic::new(3, 2)).to_string(), "-(3/4)ω");
+        assert_eq!((Transfinite::omega_power(2) + Transfinite::epsilon() * Dyadic::from(3)).to_string(), "ω^2 + 3ε");
+        assert_eq!(finite(5, 0).to_string(), "5");
+        assert_eq!(Transfinite::zero().to_string(), "0");
+    }
+}